@@ -2,6 +2,7 @@ use std::hint::black_box;
 use std::time::{Duration, Instant};
 
 use std::io::Write;
+use std::sync::Arc;
 
 use crate::{channel_with, ChannelHandles};
 use workerpool::thunk::{Thunk, ThunkWorker};
@@ -14,7 +15,7 @@ trait TestReceiver<T>: Send {
 
 impl<T> TestReceiver<T> for crate::BroadcastReceiver<T>
 where
-    T: Clone,
+    T: Clone + Send + Sync,
 {
     #[inline(always)]
     fn test_recv(&mut self) -> T {
@@ -33,7 +34,7 @@ trait TestSender<T>: Send {
 
 impl<T> TestSender<T> for crate::BroadcastSender<T>
 where
-    T: Send,
+    T: Send + Sync,
 {
     fn test_send(&mut self, value: T) {
         self.send(value);
@@ -68,13 +69,12 @@ fn nexus(
     iters: u64,
 ) -> Duration {
     let mut total_duration = Duration::new(0, 0);
+    let mut handles = channel_with(100).expect("couldn't create channel");
     for _ in 0..iters {
-        let ChannelHandles { sender, receiver } =
-            channel_with(100).expect("couldn't create channel");
-        let mut receivers: Vec<_> = (0..readers - 1).map(|_| receiver.another()).collect();
-        let mut senders: Vec<_> = (0..writers - 1).map(|_| sender.another()).collect();
-        receivers.push(receiver);
-        senders.push(sender);
+        let receivers: Vec<_> = (0..readers).map(|_| handles.receiver.another()).collect();
+        let senders: Vec<_> = (0..writers).map(|_| handles.sender.another()).collect();
+        // It would hold up the senders otherwise
+        handles.receiver.detach();
 
         for r in receivers {
             pool.execute_to(tx.clone(), Thunk::of(move || read_n(r, num * writers)));
@@ -90,6 +90,11 @@ fn nexus(
         let results = rx.iter().take(readers).count();
         total_duration += start.elapsed();
         assert_eq!(results, readers);
+        // The senders' threads may not have dropped them yet
+        while Arc::strong_count(&handles.sender.get_core()) > 3 {
+            std::thread::yield_now();
+        }
+        handles.recycle();
     }
 
     total_duration
@@ -115,3 +120,156 @@ fn test_bench() {
         let _ = std::io::stdout().flush();
     }
 }
+
+/// Compare the spsc channel against a broadcast channel with one sender and one receiver
+#[test]
+#[ignore]
+fn test_bench_spsc() {
+    let num = 1000000;
+
+    let (mut sender, mut receiver) = crate::spsc::channel(100).expect("couldn't create channel");
+    let start = Instant::now();
+    let reader = std::thread::spawn(move || {
+        for _ in 0..num {
+            black_box(receiver.recv());
+        }
+    });
+    for i in 0..num {
+        sender.send(i);
+    }
+    let _ = reader.join();
+    let spsc_duration = start.elapsed();
+
+    let ChannelHandles {
+        mut sender,
+        mut receiver,
+    } = channel_with(100).expect("couldn't create channel");
+    let start = Instant::now();
+    let reader = std::thread::spawn(move || {
+        for _ in 0..num {
+            black_box(receiver.recv());
+        }
+    });
+    for i in 0..num {
+        sender.send(i);
+    }
+    let _ = reader.join();
+    let broadcast_duration = start.elapsed();
+
+    let throughput = |duration: Duration| num as f64 / duration.as_secs_f64() / 1000000_f64;
+    println!(
+        "one_to_one spsc throughput is {} million/second, broadcast is {} million/second",
+        throughput(spsc_duration),
+        throughput(broadcast_duration)
+    );
+    let _ = std::io::stdout().flush();
+}
+
+/// How long each of 8 senders spends in `send` with a reader keeping up. Claims are a single
+/// `fetch_add` so no sender can lose a claim race. What shows up in the slowest sender's tail is
+/// time spent waiting for the senders ahead of it to publish
+#[test]
+#[ignore]
+fn test_bench_publish_latency() {
+    let num = 100000;
+    let writers = 8;
+
+    let ChannelHandles { sender, receiver } = channel_with(1024).expect("couldn't create channel");
+    let reader = std::thread::spawn(move || read_n(receiver, num * writers));
+    let senders: Vec<_> = (0..writers)
+        .map(|_| {
+            let mut sender = sender.clone();
+            std::thread::spawn(move || {
+                let mut latencies = Vec::with_capacity(num);
+                for i in 0..num {
+                    let start = Instant::now();
+                    sender.send(i);
+                    latencies.push(start.elapsed());
+                }
+                latencies.sort_unstable();
+                latencies
+            })
+        })
+        .collect();
+    drop(sender);
+    for (writer, latencies) in senders.into_iter().enumerate() {
+        let latencies = latencies.join().expect("writer panicked");
+        // In tenths of a percent
+        let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 1000];
+        println!(
+            "writer {writer} p50 {:?} p99 {:?} p99.9 {:?} max {:?}",
+            percentile(500),
+            percentile(990),
+            percentile(999),
+            percentile(1000)
+        );
+    }
+    let _ = reader.join();
+    let _ = std::io::stdout().flush();
+}
+
+/// Broadcast large messages to 4 readers, cloning the whole message for each one against
+/// sharing it through an `Arc`
+#[test]
+#[ignore]
+fn test_bench_shared() {
+    let num = 20000;
+    let readers = 4;
+    let message = vec![0_u8; 4096];
+
+    let ChannelHandles {
+        mut sender,
+        receiver,
+    } = channel_with::<Vec<u8>>(128).expect("couldn't create channel");
+    let threads: Vec<_> = (0..readers)
+        .map(|_| {
+            let mut receiver = receiver.clone();
+            std::thread::spawn(move || {
+                for _ in 0..num {
+                    black_box(receiver.recv());
+                }
+            })
+        })
+        .collect();
+    drop(receiver);
+    let start = Instant::now();
+    for _ in 0..num {
+        sender.send(message.clone());
+    }
+    threads
+        .into_iter()
+        .for_each(|reader| reader.join().expect("reader panicked"));
+    let cloned_duration = start.elapsed();
+
+    let ChannelHandles {
+        mut sender,
+        receiver,
+    } = crate::channel_shared::<Vec<u8>>(128).expect("couldn't create channel");
+    let threads: Vec<_> = (0..readers)
+        .map(|_| {
+            let mut receiver = receiver.clone();
+            std::thread::spawn(move || {
+                for _ in 0..num {
+                    black_box(receiver.recv());
+                }
+            })
+        })
+        .collect();
+    drop(receiver);
+    let start = Instant::now();
+    for _ in 0..num {
+        sender.send_shared(message.clone());
+    }
+    threads
+        .into_iter()
+        .for_each(|reader| reader.join().expect("reader panicked"));
+    let shared_duration = start.elapsed();
+
+    let throughput = |duration: Duration| num as f64 / duration.as_secs_f64() / 1000000_f64;
+    println!(
+        "{readers} readers of 4KiB messages cloned is {} million/second, shared is {} million/second",
+        throughput(cloned_duration),
+        throughput(shared_duration)
+    );
+    let _ = std::io::stdout().flush();
+}
@@ -0,0 +1,575 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use super::registry::ReceiverRegistry;
+use super::tracker::{MultiCursorTracker, SequentialProducerTracker};
+use super::wait_strategy::{SpinBlockWait, YieldWait};
+use super::{
+    checked_buffer_size, receiver, sender, Buffer, ChannelError, ChannelHandles, CloseHook, Ring,
+};
+
+/// Configure a broadcast channel before creating it
+///
+/// ```
+/// let (mut sender, mut receiver) = nexusq::ChannelBuilder::new(16)
+///     .publish_spins(10)
+///     .build::<usize>()
+///     .expect("couldn't create channel")
+///     .dissolve();
+/// sender.send(1);
+/// assert_eq!(receiver.recv(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChannelBuilder {
+    size: usize,
+    publish_spins: u32,
+    evictable: bool,
+    prefault: bool,
+    expected_receivers: usize,
+    relaxed_publish: bool,
+    credit: bool,
+    register_retries: usize,
+    register_backoff: Duration,
+    notify_granularity: usize,
+    high_watermark: Option<usize>,
+    low_watermark: Option<usize>,
+    receiver_wait: SpinBlockWait,
+    on_close: Option<Arc<CloseHook>>,
+}
+
+impl ChannelBuilder {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            publish_spins: 100,
+            evictable: false,
+            prefault: false,
+            expected_receivers: 0,
+            relaxed_publish: false,
+            credit: false,
+            register_retries: 0,
+            register_backoff: Duration::ZERO,
+            notify_granularity: 1,
+            high_watermark: None,
+            low_watermark: None,
+            receiver_wait: SpinBlockWait::new(0, 0),
+            on_close: None,
+        }
+    }
+
+    /// Senders publish in the order they claimed. A sender that finishes writing before the one
+    /// ahead of it spins this many times waiting for it before falling back to yielding.
+    /// Lower this when there are more senders than cores
+    pub fn publish_spins(mut self, publish_spins: u32) -> Self {
+        self.publish_spins = publish_spins;
+        self
+    }
+
+    /// Allow senders to [evict](sender::BroadcastSender::evict_slowest) receivers that are holding
+    /// up the channel. It costs receivers an extra atomic operation on every read so it's off by
+    /// default
+    pub fn evictable(mut self, evictable: bool) -> Self {
+        self.evictable = evictable;
+        self
+    }
+
+    /// Touch every page of the buffer while building the channel so that none of the sends take
+    /// a page fault. Useful when latency matters from the very first message. Off by default as
+    /// it makes building a large channel slower
+    pub fn prefault(mut self, prefault: bool) -> Self {
+        self.prefault = prefault;
+        self
+    }
+
+    /// Make room up front for this many receivers to be attached at once. Attaching a receiver
+    /// takes a lock on the channel's list of receivers which otherwise has to grow, while the
+    /// lock is held, as receivers are added
+    pub fn expected_receivers(mut self, expected_receivers: usize) -> Self {
+        self.expected_receivers = expected_receivers;
+        self
+    }
+
+    /// Senders publish as soon as they've written instead of waiting for the senders ahead of
+    /// them to publish first. Whichever sender fills the gap publishes everything that was
+    /// waiting on it. Receivers still see messages in the order they were claimed. Worth it with
+    /// lots of senders that don't care about the order between each other's messages. Off by
+    /// default as it costs an extra atomic store per message and [publish_spins](Self::publish_spins) is
+    /// ignored
+    pub fn relaxed_publish(mut self, relaxed_publish: bool) -> Self {
+        self.relaxed_publish = relaxed_publish;
+        self
+    }
+
+    /// Senders can only send as far as every receiver has [granted
+    /// credit](receiver::BroadcastReceiver::grant_credit) for, whatever room there is in the
+    /// channel. Receivers start without any. Useful for bridging to a protocol where the
+    /// consumer asks for messages. Off by default as senders have to check every receiver's
+    /// credit once they run out of what they've already seen
+    pub fn credit(mut self, credit: bool) -> Self {
+        self.credit = credit;
+        self
+    }
+
+    /// Only wake blocked receivers once every `granularity` messages instead of on every send so
+    /// they drain a batch each time they wake. Cuts down on wakeups with a fast sender and
+    /// receivers that don't mind a bit of latency. It's capped at half the channel's size so a
+    /// sender can't fill the channel without waking anyone.
+    ///
+    /// A receiver can be left waiting for up to `granularity - 1` messages that have been sent.
+    /// Senders wake the receivers when they have to wait for them, and when they're dropped. A
+    /// sender that's gone quiet can [flush](sender::BroadcastSender::flush) instead. Ignored
+    /// with [relaxed_publish](Self::relaxed_publish)
+    pub fn notify_granularity(mut self, granularity: usize) -> Self {
+        self.notify_granularity = granularity;
+        self
+    }
+
+    /// Senders block once `high_watermark` messages are waiting for the slowest receiver,
+    /// instead of when the channel is full. It's kept between 2 and the channel's size, which
+    /// is the default. See [low_watermark](Self::low_watermark) for when they carry on
+    pub fn high_watermark(mut self, high_watermark: usize) -> Self {
+        self.high_watermark = Some(high_watermark);
+        self
+    }
+
+    /// A sender that has blocked on the [high_watermark](Self::high_watermark) waits until
+    /// fewer than `low_watermark` messages are waiting before it carries on, rather than until
+    /// the slowest receiver has read one. Stops a sender bouncing between blocked and running
+    /// when the receivers are only just keeping up. It defaults to the high watermark, what
+    /// would be waiting with the blocked message sent, and is kept between 2 and the high
+    /// watermark so a sender can't end up waiting for its own message to be read
+    pub fn low_watermark(mut self, low_watermark: usize) -> Self {
+        self.low_watermark = Some(low_watermark);
+        self
+    }
+
+    /// How receivers wait for something to be sent. By default they block straight away. A
+    /// [TimedSpinWait](crate::wait_strategy::TimedSpinWait) spins for a while first, which
+    /// keeps the latency down for receivers that have a core to themselves
+    ///
+    /// ```
+    /// use nexusq::wait_strategy::TimedSpinWait;
+    /// let (mut sender, mut receiver) = nexusq::ChannelBuilder::new(8)
+    ///     .receiver_wait(TimedSpinWait::new(std::time::Duration::from_micros(20)))
+    ///     .build()
+    ///     .expect("couldn't create channel")
+    ///     .dissolve();
+    /// sender.send(1);
+    /// assert_eq!(receiver.recv(), 1);
+    /// ```
+    pub fn receiver_wait(mut self, receiver_wait: impl Into<SpinBlockWait>) -> Self {
+        self.receiver_wait = receiver_wait.into();
+        self
+    }
+
+    /// A new receiver registers at the latest message. On a busy channel the other receivers can
+    /// move past it before it's done, which fails with
+    /// [RegistrationFailed](receiver::ReceiverError::RegistrationFailed). This is how many more
+    /// times [add_stream](receiver::BroadcastReceiver::add_stream),
+    /// [ReceiverFactory](receiver::ReceiverFactory) and creating a receiver from a sender try
+    /// again before giving up. 0 by default
+    pub fn register_retries(mut self, register_retries: usize) -> Self {
+        self.register_retries = register_retries;
+        self
+    }
+
+    /// How long a new receiver waits before trying to register again. By default it only
+    /// yields
+    pub fn register_backoff(mut self, register_backoff: Duration) -> Self {
+        self.register_backoff = register_backoff;
+        self
+    }
+
+    /// Run `f` once the last sender and receiver have been dropped, after the messages still in
+    /// the channel have been dropped. Useful for releasing something that lives as long as the
+    /// channel. With [build_pool](Self::build_pool) it runs once every channel in the pool has
+    /// gone. Clones of the builder share the same `f` so it waits for them too.
+    ///
+    /// `f` runs in whichever thread drops the last handle. A panic in it carries on out of that
+    /// drop
+    pub fn on_close(mut self, f: impl FnOnce() + Send + 'static) -> Self {
+        self.on_close = Some(Arc::new(CloseHook::new(f)));
+        self
+    }
+
+    pub fn build<T>(self) -> Result<ChannelHandles<T>, ChannelError> {
+        let buffer_size = checked_buffer_size(self.size)?;
+        let buffer = self.new_buffer(buffer_size);
+        // Nothing else can use a new buffer
+        unsafe { self.build_in(buffer, 0, buffer_size) }
+    }
+
+    /// Build `streams` channels that all keep their messages in a single allocation. Useful when
+    /// an application needs a lot of small channels of the same type.
+    ///
+    /// The channels are completely independent of each other. Each has its own senders,
+    /// receivers and trackers so a slow receiver on one channel never holds up the senders on
+    /// another. They only share memory which is freed once every one of them has been dropped.
+    ///
+    /// ```
+    /// let mut streams = nexusq::ChannelBuilder::new(4)
+    ///     .build_pool::<usize>(3)
+    ///     .expect("couldn't create channels");
+    /// for (stream, handles) in streams.iter_mut().enumerate() {
+    ///     handles.sender.send(stream);
+    /// }
+    /// for (stream, handles) in streams.iter_mut().enumerate() {
+    ///     assert_eq!(handles.receiver.recv(), stream);
+    /// }
+    /// ```
+    pub fn build_pool<T>(self, streams: usize) -> Result<Vec<ChannelHandles<T>>, ChannelError> {
+        let buffer_size = checked_buffer_size(self.size)?;
+        let total = buffer_size
+            .checked_mul(streams)
+            .filter(|total| *total <= isize::MAX as usize)
+            .ok_or(ChannelError::BufferTooBig)?;
+        let buffer = self.new_buffer(total);
+        (0..streams)
+            // Every channel gets its own part of the buffer
+            .map(|stream| unsafe {
+                self.build_in(buffer.clone(), stream * buffer_size, buffer_size)
+            })
+            .collect()
+    }
+
+    fn new_buffer<T>(&self, len: usize) -> Arc<Buffer<T>> {
+        let buffer = Buffer::new(len);
+        if self.prefault {
+            buffer.prefault();
+        }
+        buffer
+    }
+
+    /// # Safety
+    /// No other ring can be using the part of `buffer` the channel would use
+    unsafe fn build_in<T>(
+        &self,
+        buffer: Arc<Buffer<T>>,
+        offset: usize,
+        buffer_size: usize,
+    ) -> Result<ChannelHandles<T>, ChannelError> {
+        let sender_tracker = if self.relaxed_publish {
+            SequentialProducerTracker::relaxed(self.receiver_wait.clone(), buffer_size)
+        } else {
+            SequentialProducerTracker::with_publish_wait(
+                self.receiver_wait.clone(),
+                YieldWait::new(self.publish_spins),
+            )
+            .with_notify_granularity(self.notify_granularity.min(buffer_size / 2))
+        };
+        let mut ring = Ring::in_buffer(
+            buffer,
+            offset,
+            buffer_size,
+            sender_tracker,
+            MultiCursorTracker::new(buffer_size, SpinBlockWait::new(0, 0))?,
+        );
+        ring.evictable = self.evictable;
+        ring.credited = self.credit;
+        ring.register_retries = self.register_retries;
+        ring.register_backoff = self.register_backoff;
+        ring.high_watermark = self.high_watermark.map_or(buffer_size, |high| {
+            high.clamp(2.min(buffer_size), buffer_size)
+        });
+        ring.low_watermark = self.low_watermark.map_or(ring.high_watermark, |low| {
+            low.clamp(2.min(ring.high_watermark), ring.high_watermark)
+        });
+        ring.on_close = self.on_close.clone();
+        ring.receivers = ReceiverRegistry::with_capacity(self.expected_receivers);
+        let core = Arc::new(ring);
+        let sender = sender::BroadcastSender::from(core.clone());
+        let receiver = receiver::BroadcastReceiver::try_from(core)?;
+        Ok(ChannelHandles::new(sender, receiver))
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+
+    #[test]
+    fn no_publish_spins() {
+        let num = 1000;
+        let (sender, mut receiver) = ChannelBuilder::new(8)
+            .publish_spins(0)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let writers: Vec<_> = (0..3)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num {
+                        sender.send((writer, i));
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut next = [0; 3];
+        for _ in 0..num * 3 {
+            let (writer, i) = receiver.recv();
+            assert_eq!(next[writer], i);
+            next[writer] += 1;
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+    }
+
+    #[test]
+    fn notify_granularity() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(16)
+            .notify_granularity(100)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        // It doesn't deadlock when the sender fills the channel
+        let reader = std::thread::spawn(move || {
+            for i in 0..1005 {
+                assert_eq!(receiver.recv(), i);
+            }
+            receiver
+        });
+        for i in 0..1005 {
+            sender.send(i);
+        }
+        sender.flush();
+        let mut receiver = reader.join().expect("reader panicked");
+        let reader = std::thread::spawn(move || receiver.recv());
+        sender.send(1005);
+        sender.flush();
+        assert_eq!(reader.join().expect("reader panicked"), 1005);
+    }
+
+    #[test]
+    fn receiver_wait() {
+        use crate::wait_strategy::TimedSpinWait;
+        let spin_for = Duration::from_micros(50);
+        let (mut sender, mut receiver) = ChannelBuilder::new(8)
+            .receiver_wait(TimedSpinWait::new(spin_for))
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(
+            sender.stats().receiver_wait_strategy().spin_for(),
+            Some(spin_for)
+        );
+        let writer = std::thread::spawn(move || {
+            for i in 0..100 {
+                sender.send(i);
+            }
+        });
+        for i in 0..100 {
+            assert_eq!(receiver.recv(), i);
+        }
+        writer.join().expect("writer panicked");
+    }
+
+    #[test]
+    fn watermarks() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(16)
+            .high_watermark(8)
+            .low_watermark(4)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        // The receiver is holding 0 so 8 is the first send that blocks
+        for i in 0..8 {
+            sender.send(i);
+        }
+        let writer = std::thread::spawn(move || sender.send(8));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(!writer.is_finished());
+        // Freeing up one slot isn't enough to get it going again
+        for i in 0..5 {
+            assert_eq!(receiver.recv(), i);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(!writer.is_finished());
+        // Down to 2 waiting, 6 and 7
+        assert_eq!(receiver.recv(), 5);
+        writer.join().expect("writer panicked");
+        for i in 6..9 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn relaxed_publish() {
+        let num = 5000;
+        let (sender, mut receiver) = ChannelBuilder::new(16)
+            .relaxed_publish(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num {
+                        sender.send((writer, i));
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut next = [0; 4];
+        for _ in 0..num * 4 {
+            let (writer, i) = receiver.recv();
+            assert_eq!(next[writer], i);
+            next[writer] += 1;
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn expected_receivers() {
+        let (_, receiver) = ChannelBuilder::new(4)
+            .expected_receivers(16)
+            .build::<usize>()
+            .expect("couldn't create channel")
+            .dissolve();
+        let receivers: Vec<_> = (0..15).map(|_| receiver.clone()).collect();
+        assert_eq!(receiver.stats().receiver_positions().len(), 16);
+        assert_eq!(receiver.get_core().receivers().capacity(), 16);
+        drop(receivers);
+    }
+
+    #[test]
+    fn prefault() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(4096)
+            .prefault(true)
+            .build::<[u64; 3]>()
+            .expect("couldn't create channel")
+            .dissolve();
+        for i in 0..5000 {
+            sender.send([i; 3]);
+            assert_eq!(receiver.recv(), [i; 3]);
+        }
+        // There's nothing to touch for zero sized types
+        let (mut sender, mut receiver) = ChannelBuilder::new(8)
+            .prefault(true)
+            .build::<()>()
+            .expect("couldn't create channel")
+            .dissolve();
+        sender.send(());
+        receiver.recv();
+    }
+
+    #[test]
+    fn register_retries() {
+        let handles = ChannelBuilder::new(2)
+            .register_retries(1000)
+            .register_backoff(Duration::from_micros(1))
+            .build::<usize>()
+            .expect("couldn't create channel");
+        let core = handles.sender.get_core();
+        assert_eq!(core.register_retries(), 1000);
+        let (mut sender, mut receiver) = handles.dissolve();
+        let done = Arc::new(core::sync::atomic::AtomicBool::new(false));
+        let writer = {
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let mut i = 0;
+                while !done.load(core::sync::atomic::Ordering::Relaxed) {
+                    sender.send(i);
+                    i += 1;
+                }
+            })
+        };
+        let reader = {
+            let done = done.clone();
+            std::thread::spawn(move || {
+                while !done.load(core::sync::atomic::Ordering::Relaxed) {
+                    receiver.try_recv().ok();
+                }
+            })
+        };
+        // Late joiners on a channel that keeps wrapping don't fail
+        let factory = receiver::ReceiverFactory::from(core);
+        for _ in 0..1000 {
+            factory.receiver().expect("couldn't join the channel");
+        }
+        done.store(true, core::sync::atomic::Ordering::Relaxed);
+        writer.join().expect("writer panicked");
+        reader.join().expect("reader panicked");
+    }
+
+    #[test]
+    fn on_close() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let values = Arc::new(());
+        let closed = Arc::new(AtomicUsize::new(0));
+        let (mut sender, receiver) = {
+            let values = values.clone();
+            let closed = closed.clone();
+            ChannelBuilder::new(4)
+                .on_close(move || {
+                    // Everything in the channel has already been dropped. That leaves this one
+                    // and the original
+                    assert_eq!(Arc::strong_count(&values), 2);
+                    closed.fetch_add(1, Ordering::Relaxed);
+                })
+                .build()
+                .expect("couldn't create channel")
+                .dissolve()
+        };
+        sender.send(values.clone());
+        let other = sender.clone();
+        drop(sender);
+        drop(receiver);
+        assert_eq!(closed.load(Ordering::Relaxed), 0);
+        drop(other);
+        assert_eq!(closed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn on_close_pool() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let mut streams = {
+            let closed = closed.clone();
+            ChannelBuilder::new(4)
+                .on_close(move || closed.store(true, Ordering::Relaxed))
+                .build_pool::<usize>(2)
+                .expect("couldn't create channels")
+        };
+        let last = streams.pop();
+        drop(streams);
+        assert!(!closed.load(Ordering::Relaxed));
+        drop(last);
+        assert!(closed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn pooled_streams_are_independent() {
+        let streams = ChannelBuilder::new(4)
+            .build_pool::<String>(3)
+            .expect("couldn't create channels");
+        let mut handles: Vec<_> = streams.into_iter().map(ChannelHandles::dissolve).collect();
+        let (mut full, stuck) = handles.remove(0);
+        // Nobody reads the first stream once it's full but that doesn't stop the others
+        for i in 0..4 {
+            full.send(i.to_string());
+        }
+        for i in 0..20 {
+            for (stream, (sender, receiver)) in handles.iter_mut().enumerate() {
+                sender.send(format!("{stream}-{i}"));
+                assert_eq!(receiver.recv(), format!("{stream}-{i}"));
+            }
+        }
+        // The memory stays around until the last stream is gone
+        drop(stuck);
+        let (mut sender, mut receiver) = handles.pop().expect("there are two streams left");
+        drop(handles);
+        sender.send(String::from("still here"));
+        assert_eq!(receiver.recv(), "still here");
+    }
+}
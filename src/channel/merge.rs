@@ -0,0 +1,259 @@
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::Context;
+use std::future::Future;
+
+use crate::utils::ThreadWaker;
+use crate::BroadcastReceiver;
+
+/// Receive from several broadcast channels at once. Every value comes back with the index of the
+/// receiver it came from. When more than one receiver has something waiting they take turns so
+/// a busy channel can't starve the others
+///
+/// ```
+/// let (mut first, first_receiver) = nexusq::channel(4).expect("couldn't create channel").dissolve();
+/// let (mut second, second_receiver) = nexusq::channel(4).expect("couldn't create channel").dissolve();
+/// let mut merge = nexusq::Merge::new(vec![first_receiver, second_receiver]);
+/// second.send("b");
+/// first.send("a");
+/// assert_eq!(merge.recv(), (0, "a"));
+/// assert_eq!(merge.recv(), (1, "b"));
+/// ```
+#[derive(Debug)]
+pub struct Merge<T> {
+    sources: Vec<BroadcastReceiver<T>>,
+    // Where the next search for a value starts
+    next: usize,
+}
+
+impl<T> Merge<T> {
+    /// # Panics
+    /// Panics if there aren't any sources as there'd be nothing to receive from
+    pub fn new(sources: Vec<BroadcastReceiver<T>>) -> Self {
+        assert!(!sources.is_empty(), "a merge needs at least one source");
+        Self { sources, next: 0 }
+    }
+
+    /// Get the receivers back. They're in the same order they were given in
+    pub fn into_inner(self) -> Vec<BroadcastReceiver<T>> {
+        self.sources
+    }
+}
+
+impl<T> Merge<T>
+where
+    T: Clone,
+{
+    /// Read the next value from whichever source has one without waiting. Returns the index of
+    /// the source along with the value or None if none of them have anything
+    pub fn try_recv(&mut self) -> Option<(usize, T)> {
+        let (index, value) = try_recv_from(&mut self.sources, self.next)?;
+        self.next = index + 1;
+        Some((index, value))
+    }
+
+    /// Read the next value from whichever source has one, blocking until one does. Returns the
+    /// index of the source along with the value
+    pub fn recv(&mut self) -> (usize, T) {
+        loop {
+            if let Some(value) = self.try_recv() {
+                return value;
+            }
+            wait_any(&mut self.sources);
+        }
+    }
+}
+
+/// Receive from several broadcast channels at once, always preferring the ones that come first.
+/// A source is only read from when every source before it is empty. Every value comes back with
+/// the index of the receiver it came from.
+///
+/// A busy high priority source can starve the rest. A [starvation
+/// guard](Self::starvation_guard) gives the others a turn every so often
+///
+/// ```
+/// let (mut control, control_receiver) = nexusq::channel(4).expect("couldn't create channel").dissolve();
+/// let (mut data, data_receiver) = nexusq::channel(4).expect("couldn't create channel").dissolve();
+/// let mut merge = nexusq::MergeBiased::new(vec![control_receiver, data_receiver]);
+/// data.send("data");
+/// control.send("stop");
+/// assert_eq!(merge.recv(), (0, "stop"));
+/// assert_eq!(merge.recv(), (1, "data"));
+/// ```
+#[derive(Debug)]
+pub struct MergeBiased<T> {
+    sources: Vec<BroadcastReceiver<T>>,
+    guard: Option<usize>,
+    // Values received since lower priorities were last given a turn
+    since_turn: usize,
+    last: usize,
+}
+
+impl<T> MergeBiased<T> {
+    /// The sources are given in priority order, highest first
+    ///
+    /// # Panics
+    /// Panics if there aren't any sources as there'd be nothing to receive from
+    pub fn new(sources: Vec<BroadcastReceiver<T>>) -> Self {
+        assert!(!sources.is_empty(), "a merge needs at least one source");
+        Self {
+            sources,
+            guard: None,
+            since_turn: 0,
+            last: 0,
+        }
+    }
+
+    /// After every `every` values the next one is looked for starting at the source after the
+    /// one that was last read from rather than the highest priority. That way a lower priority
+    /// source gets at least one value through in every `every + 1` when it has one waiting.
+    /// There's no guard by default
+    pub fn starvation_guard(mut self, every: usize) -> Self {
+        self.guard = Some(every);
+        self
+    }
+
+    /// Get the receivers back. They're in the same order they were given in
+    pub fn into_inner(self) -> Vec<BroadcastReceiver<T>> {
+        self.sources
+    }
+}
+
+impl<T> MergeBiased<T>
+where
+    T: Clone,
+{
+    /// Read the next value from the highest priority source that has one without waiting.
+    /// Returns the index of the source along with the value or None if none of them have anything
+    pub fn try_recv(&mut self) -> Option<(usize, T)> {
+        let give_turn = matches!(self.guard, Some(every) if self.since_turn >= every);
+        let start = if give_turn { self.last + 1 } else { 0 };
+        let (index, value) = try_recv_from(&mut self.sources, start)?;
+        self.since_turn = if give_turn { 0 } else { self.since_turn + 1 };
+        self.last = index;
+        Some((index, value))
+    }
+
+    /// Read the next value from the highest priority source that has one, blocking until one
+    /// does. Returns the index of the source along with the value
+    pub fn recv(&mut self) -> (usize, T) {
+        loop {
+            if let Some(value) = self.try_recv() {
+                return value;
+            }
+            wait_any(&mut self.sources);
+        }
+    }
+}
+
+/// Try each source in turn starting at `start` and wrapping around
+fn try_recv_from<T: Clone>(
+    sources: &mut [BroadcastReceiver<T>],
+    start: usize,
+) -> Option<(usize, T)> {
+    let num_sources = sources.len();
+    for offset in 0..num_sources {
+        let index = (start + offset) % num_sources;
+        // An evicted source is reattached the next time it's tried
+        if let Ok(value) = sources[index].try_recv() {
+            return Some((index, value));
+        }
+    }
+    None
+}
+
+/// Block until something is published to one of the sources or it looks like it might have been
+fn wait_any<T>(sources: &mut [BroadcastReceiver<T>]) {
+    let mut listeners: Vec<_> = sources.iter().map(BroadcastReceiver::listen).collect();
+    // Something could have been published before we started listening
+    if sources.iter().any(BroadcastReceiver::has_pending) {
+        return;
+    }
+    let waker = ThreadWaker::current();
+    let mut context = Context::from_waker(&waker);
+    loop {
+        for listener in listeners.iter_mut() {
+            if Pin::new(listener).poll(&mut context).is_ready() {
+                return;
+            }
+        }
+        std::thread::park();
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::*;
+
+    #[test]
+    fn takes_turns() {
+        let (mut busy, busy_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let (mut quiet, quiet_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut merge = Merge::new(vec![busy_receiver, quiet_receiver]);
+        assert!(merge.try_recv().is_none());
+        for i in 0..4 {
+            busy.send(i);
+        }
+        quiet.send(10);
+        quiet.send(11);
+        let received: Vec<_> = (0..6).map(|_| merge.recv()).collect();
+        assert_eq!(
+            received,
+            vec![(0, 0), (1, 10), (0, 1), (1, 11), (0, 2), (0, 3)]
+        );
+        assert!(merge.try_recv().is_none());
+    }
+
+    #[test]
+    fn biased() {
+        let (mut high, high_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let (mut low, low_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut merge = MergeBiased::new(vec![high_receiver, low_receiver]);
+        assert!(merge.try_recv().is_none());
+        low.send(10);
+        low.send(11);
+        for i in 0..4 {
+            high.send(i);
+        }
+        let received: Vec<_> = (0..6).map(|_| merge.recv()).collect();
+        assert_eq!(
+            received,
+            vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 10), (1, 11)]
+        );
+    }
+
+    #[test]
+    fn starvation_guard() {
+        let (mut high, high_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let (mut low, low_receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut merge = MergeBiased::new(vec![high_receiver, low_receiver]).starvation_guard(2);
+        low.send(10);
+        low.send(11);
+        for i in 0..5 {
+            high.send(i);
+        }
+        let received: Vec<_> = (0..7).map(|_| merge.recv()).collect();
+        assert_eq!(
+            received,
+            vec![(0, 0), (0, 1), (1, 10), (0, 2), (0, 3), (1, 11), (0, 4)]
+        );
+    }
+
+    #[test]
+    fn wakes_for_any_source() {
+        let handles: Vec<_> = (0..3)
+            .map(|_| channel(4).expect("couldn't create channel").dissolve())
+            .collect();
+        let (mut senders, receivers): (Vec<_>, Vec<_>) = handles.into_iter().unzip();
+        let mut merge = Merge::new(receivers);
+        let reader = std::thread::spawn(move || (0..3).map(|_| merge.recv()).collect::<Vec<_>>());
+        for (index, sender) in senders.iter_mut().enumerate().rev() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            sender.send(index * 10);
+        }
+        assert_eq!(
+            reader.join().expect("reader panicked"),
+            vec![(2, 20), (1, 10), (0, 0)]
+        );
+    }
+}
@@ -1,17 +1,35 @@
+mod builder;
+mod merge;
+pub mod mpsc;
 pub mod receiver;
+mod registry;
 pub mod sender;
+mod sequence;
+pub mod shm;
+pub mod spsc;
+mod stats;
+pub mod steal;
 mod tracker;
 pub mod wait_strategy;
+pub mod watch;
 
 use thiserror::Error as ThisError;
 
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
 
 use crate::channel::tracker::Tracker;
+use crate::utils::FastMod;
+pub use builder::ChannelBuilder;
+pub use merge::{Merge, MergeBiased};
 use receiver::{BroadcastReceiver, ReceiverError};
+use registry::ReceiverRegistry;
 use sender::BroadcastSender;
+pub use sequence::Sequence;
+pub use stats::ChannelStats;
 use tracker::{MultiCursorTracker, ProducerTracker, ReceiverTracker, SequentialProducerTracker};
 use wait_strategy::{SpinBlockWait, WaitStrategy};
 
@@ -23,13 +41,21 @@ pub enum ChannelError {
     SetupFailed(#[from] Box<dyn std::error::Error>),
     #[error("requested buffer too big")]
     BufferTooBig,
+    #[error("the memory region is too small to hold a channel")]
+    RegionTooSmall,
+    #[error("the memory region doesn't hold a channel for this type")]
+    IncompatibleLayout,
+    #[error("the byte budget is too small for a single message")]
+    BudgetTooSmall,
 }
 
 impl From<tracker::TrackerError> for ChannelError {
     fn from(error: tracker::TrackerError) -> Self {
         match error {
             tracker::TrackerError::InvalidSize => Self::InvalidSize,
-            tracker::TrackerError::PositionTooOld => Self::SetupFailed(Box::new(error)),
+            tracker::TrackerError::PositionTooOld | tracker::TrackerError::PositionAliased => {
+                Self::SetupFailed(Box::new(error))
+            }
         }
     }
 }
@@ -49,67 +75,503 @@ pub trait Core {
     type ReadTracker: ReceiverTracker;
     fn sender_tracker(&self) -> &Self::SendTracker;
     fn reader_tracker(&self) -> &Self::ReadTracker;
-    fn ring(&self) -> *mut Vec<Self::T>;
     fn capacity(&self) -> usize;
 }
 
 #[derive(Debug)]
-pub struct Ring<T> {
-    ring: *mut Vec<T>,
+pub struct Ring<
+    T,
+    ST = SequentialProducerTracker<SpinBlockWait>,
+    RT = MultiCursorTracker<SpinBlockWait>,
+> where
+    ST: Tracker,
+{
+    // The first of this ring's slots in `buffer`
+    ring: *mut T,
+    buffer: Arc<Buffer<T>>,
     capacity: usize,
     // is there a better way than events?
-    sender_tracker: SequentialProducerTracker<SpinBlockWait>,
+    sender_tracker: ST,
     // Reference to each reader to get their position. It should be sorted(how..?)
-    reader_tracker: MultiCursorTracker<SpinBlockWait>,
+    reader_tracker: RT,
+    receivers: ReceiverRegistry,
+    // Receivers only do the extra work needed to be evicted safely when this is set
+    evictable: bool,
+    // Senders can only send what every receiver has granted credit for
+    credited: bool,
+    // How many more times a new receiver tries to register after finding the channel has
+    // already moved past where it tried, and how long it waits in between
+    register_retries: usize,
+    register_backoff: core::time::Duration,
+    // Senders block once this many messages are waiting for the slowest receiver and then wait
+    // for it to get down to below the low watermark
+    high_watermark: usize,
+    low_watermark: usize,
+    // How many broadcast senders there are
+    senders: AtomicUsize,
+    // Set by a sender shutting the channel down. Receivers that have read everything get
+    // ReceiverError::Disconnected
+    closed: AtomicBool,
+    // How many senders are waiting for the receivers to read everything
+    drain_waiters: AtomicUsize,
+    // Values receivers have finished with, waiting for a sender to reuse them
+    returned: Mutex<Vec<T>>,
+    // Dropped after the values in the ring. Shared by every ring in a pool
+    on_close: Option<Arc<CloseHook>>,
+    #[cfg(feature = "heartbeat")]
+    heartbeat: Heartbeat,
 }
 
-unsafe impl<T> Send for Ring<T> {}
-unsafe impl<T> Sync for Ring<T> {}
+// Values are moved between threads by the ring and any number of receivers can be reading the
+// same value at once
+unsafe impl<T: Send, ST: Tracker, RT> Send for Ring<T, ST, RT> {}
+unsafe impl<T: Send + Sync, ST: Tracker, RT> Sync for Ring<T, ST, RT> {}
 
-impl<T> Drop for Ring<T> {
+impl<T, ST, RT> Drop for Ring<T, ST, RT>
+where
+    ST: Tracker,
+{
     fn drop(&mut self) {
-        let current = self.sender_tracker.current();
+        unsafe { self.drop_values() }
+    }
+}
+
+/// When a message was last published
+#[cfg(feature = "heartbeat")]
+#[derive(Debug)]
+pub(crate) struct Heartbeat {
+    created: std::time::Instant,
+    // Nanoseconds after created. It's 0 until something is published
+    last: core::sync::atomic::AtomicU64,
+}
+
+#[cfg(feature = "heartbeat")]
+impl Heartbeat {
+    fn new() -> Self {
+        Self {
+            created: std::time::Instant::now(),
+            last: Default::default(),
+        }
+    }
+
+    fn beat(&self) {
+        let now = self.created.elapsed().as_nanos() as u64;
+        // Senders racing each other can't move it backwards
+        self.last.fetch_max(now, Ordering::Relaxed);
+    }
+
+    fn elapsed(&self) -> core::time::Duration {
+        let last = core::time::Duration::from_nanos(self.last.load(Ordering::Relaxed));
+        self.created.elapsed().saturating_sub(last)
+    }
+}
+
+/// Runs a callback once the last ring holding it has been dropped
+pub(crate) struct CloseHook(Mutex<Option<Box<dyn FnOnce() + Send>>>);
+
+impl CloseHook {
+    pub(crate) fn new(f: impl FnOnce() + Send + 'static) -> Self {
+        Self(Mutex::new(Some(Box::new(f))))
+    }
+}
+
+impl Drop for CloseHook {
+    fn drop(&mut self) {
+        let f = self
+            .0
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(f) = f {
+            f();
+        }
+    }
+}
+
+impl core::fmt::Debug for CloseHook {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("CloseHook")
+    }
+}
+
+/// Keeps receivers waking a draining sender while it's alive
+pub(crate) struct DrainWaiter<'a>(&'a AtomicUsize);
+
+impl Drop for DrainWaiter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Memory for the slots of one or more rings. Each ring drops the values in its own slots. This
+/// only frees the memory once every ring using it is gone
+#[derive(Debug)]
+pub(crate) struct Buffer<T> {
+    start: *mut T,
+    // What the memory was allocated with which can be more than asked for
+    allocated: usize,
+}
+
+impl<T> Buffer<T> {
+    pub(crate) fn new(len: usize) -> Arc<Self> {
+        let mut memory = core::mem::ManuallyDrop::new(Vec::<T>::with_capacity(len));
+        Arc::new(Self {
+            start: memory.as_mut_ptr(),
+            allocated: memory.capacity(),
+        })
+    }
+}
+
+impl<T> Buffer<T> {
+    /// Write to every page of the buffer so that it's resident before it's used. Otherwise the
+    /// first pass through the ring takes a page fault every few slots
+    pub(crate) fn prefault(&self) {
+        // The smallest page size in common use
+        const PAGE_SIZE: usize = 4096;
+        let len = self.allocated.saturating_mul(core::mem::size_of::<T>());
+        let start = self.start.cast::<u8>();
+        // Nothing has been written to the buffer yet so there's no value to overwrite
         unsafe {
-            if current < 0 {
-                (*self.ring).set_len(0);
-            } else if (current as usize) < self.capacity {
-                (*self.ring).set_len(current as usize);
+            for offset in (0..len).step_by(PAGE_SIZE) {
+                start.add(offset).write_volatile(0);
+            }
+            if len > 0 {
+                start.add(len - 1).write_volatile(0);
             }
-            drop(Box::from_raw(self.ring));
         }
     }
 }
 
+impl<T> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Vec::from_raw_parts(self.start, 0, self.allocated));
+        }
+    }
+}
+
+/// Round the requested size up to the next power of 2
+fn checked_buffer_size(buffer_size: usize) -> Result<usize, ChannelError> {
+    let buffer_size = if let Some(bs) = buffer_size.checked_next_power_of_two() {
+        bs
+    } else {
+        return Err(ChannelError::BufferTooBig);
+    };
+    if buffer_size > isize::MAX as usize {
+        return Err(ChannelError::BufferTooBig);
+    }
+    Ok(buffer_size)
+}
+
 impl<T> Ring<T> {
-    pub(crate) fn new(mut buffer_size: usize) -> Result<Self, ChannelError> {
-        buffer_size = if let Some(bs) = buffer_size.checked_next_power_of_two() {
-            bs
+    pub(crate) fn new(buffer_size: usize) -> Result<Self, ChannelError> {
+        let buffer_size = checked_buffer_size(buffer_size)?;
+        Ok(Self::with_trackers(
+            buffer_size,
+            SequentialProducerTracker::new(SpinBlockWait::new(0, 0)),
+            MultiCursorTracker::new(buffer_size, SpinBlockWait::new(0, 0))?,
+        ))
+    }
+
+    /// Drop everything in the ring and put it back how it was when it was created so it can be
+    /// used again without allocating
+    ///
+    /// # Safety
+    /// Nothing else can be using the ring and there can't be any receivers registered
+    pub(crate) unsafe fn recycle(&self) {
+        self.drop_values();
+        self.sender_tracker.reset();
+        self.reader_tracker.reset();
+        self.closed.store(false, Ordering::Release);
+    }
+
+    /// Mark the channel as closed and wake up the receivers waiting on it
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.sender_tracker.wait_strategy().notify();
+    }
+}
+
+impl<T, ST, RT> Ring<T, ST, RT>
+where
+    ST: Tracker,
+{
+    /// Drop the values in the slots that have been written to
+    ///
+    /// # Safety
+    /// Nothing can be using the ring and the slots are left empty
+    unsafe fn drop_values(&self) {
+        // Slots are written in order so every one up to the last published id has a value
+        let written = (self.sender_tracker.current() + 1).clamp(0, self.capacity as isize);
+        core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(
+            self.ring,
+            written as usize,
+        ));
+    }
+
+    /// `buffer_size` must already be a power of 2
+    pub(crate) fn with_trackers(
+        buffer_size: usize,
+        sender_tracker: ST,
+        reader_tracker: RT,
+    ) -> Self {
+        // Nothing else can use a new buffer
+        unsafe {
+            Self::in_buffer(
+                Buffer::new(buffer_size),
+                0,
+                buffer_size,
+                sender_tracker,
+                reader_tracker,
+            )
+        }
+    }
+
+    /// Use the `buffer_size` slots in `buffer` starting at `offset`. `buffer_size` must already
+    /// be a power of 2
+    ///
+    /// # Safety
+    /// No other ring can be using any of those slots
+    pub(crate) unsafe fn in_buffer(
+        buffer: Arc<Buffer<T>>,
+        offset: usize,
+        buffer_size: usize,
+        sender_tracker: ST,
+        reader_tracker: RT,
+    ) -> Self {
+        debug_assert!(buffer_size.is_power_of_two());
+        assert!(
+            offset + buffer_size <= buffer.allocated,
+            "the ring doesn't fit in the buffer"
+        );
+        Self {
+            ring: buffer.start.add(offset),
+            buffer,
+            capacity: buffer_size,
+            sender_tracker,
+            reader_tracker,
+            receivers: Default::default(),
+            evictable: false,
+            credited: false,
+            register_retries: 0,
+            register_backoff: core::time::Duration::ZERO,
+            high_watermark: buffer_size,
+            low_watermark: buffer_size,
+            senders: Default::default(),
+            closed: Default::default(),
+            drain_waiters: Default::default(),
+            returned: Default::default(),
+            on_close: None,
+            #[cfg(feature = "heartbeat")]
+            heartbeat: Heartbeat::new(),
+        }
+    }
+
+    /// Record that a message has just been published. Does nothing without the heartbeat feature
+    #[inline(always)]
+    pub(crate) fn beat(&self) {
+        #[cfg(feature = "heartbeat")]
+        self.heartbeat.beat();
+    }
+
+    /// How long it's been since a message was last published, or since the ring was made if
+    /// nothing has been
+    #[cfg(feature = "heartbeat")]
+    pub(crate) fn last_publish_elapsed(&self) -> core::time::Duration {
+        self.heartbeat.elapsed()
+    }
+
+    /// The broadcast receivers that are currently attached
+    pub(crate) fn receivers(&self) -> &ReceiverRegistry {
+        &self.receivers
+    }
+
+    /// Can a sender evict receivers from this ring
+    #[inline(always)]
+    pub(crate) fn evictable(&self) -> bool {
+        self.evictable
+    }
+
+    /// Do senders have to wait for credit from the receivers
+    #[inline(always)]
+    pub(crate) fn credited(&self) -> bool {
+        self.credited
+    }
+
+    /// How full the channel gets before senders block and how far it has to drain before they
+    /// carry on
+    pub(crate) fn watermarks(&self) -> (usize, usize) {
+        (self.high_watermark, self.low_watermark)
+    }
+
+    /// How many times a new receiver tries to register again before giving up
+    pub(crate) fn register_retries(&self) -> usize {
+        self.register_retries
+    }
+
+    /// Wait before a new receiver tries to register again
+    pub(crate) fn register_backoff(&self) {
+        if self.register_backoff.is_zero() {
+            std::thread::yield_now();
         } else {
-            return Err(ChannelError::BufferTooBig);
-        };
-        if buffer_size > isize::MAX as usize {
-            return Err(ChannelError::BufferTooBig);
+            std::thread::sleep(self.register_backoff);
         }
-        let mut ring = Box::new(Vec::with_capacity(buffer_size));
-        unsafe {
-            ring.set_len(buffer_size);
+    }
+
+    /// Keep a value a receiver has finished with for a sender to reuse. It's dropped if there
+    /// are already as many waiting as the ring holds
+    pub(crate) fn return_buffer(&self, value: T) {
+        let mut returned = self.returned.lock().unwrap_or_else(PoisonError::into_inner);
+        if returned.len() < self.capacity {
+            returned.push(value);
+            return;
         }
+        // Don't drop it while holding the lock
+        drop(returned);
+        drop(value);
+    }
 
-        let ring = Box::into_raw(ring);
+    /// Take the value that was returned most recently
+    pub(crate) fn take_buffer(&self) -> Option<T> {
+        self.returned
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+    }
 
-        Ok(Self {
-            ring,
-            capacity: buffer_size,
-            sender_tracker: SequentialProducerTracker::new(SpinBlockWait::new(0, 0)),
-            reader_tracker: MultiCursorTracker::new(buffer_size, SpinBlockWait::new(0, 0))?,
-        })
+    pub(crate) fn add_sender(&self) {
+        self.senders.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Returns how many senders are left
+    pub(crate) fn remove_sender(&self) -> usize {
+        self.senders.fetch_sub(1, Ordering::AcqRel) - 1
+    }
+
+    /// Has a sender closed the channel
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Is a sender waiting for the receivers to read everything
+    pub(crate) fn draining(&self) -> bool {
+        // SeqCst pairs with the receiver's unread flag. Either the receiver sees that someone's
+        // waiting or the waiter sees that the receiver has read its slot
+        self.drain_waiters.load(Ordering::SeqCst) > 0
+    }
+
+    /// Tell receivers that a sender is waiting for them to read everything until the returned
+    /// guard is dropped
+    pub(crate) fn wait_for_drain(&self) -> DrainWaiter<'_> {
+        self.drain_waiters.fetch_add(1, Ordering::SeqCst);
+        DrainWaiter(&self.drain_waiters)
+    }
+
+    /// How many broadcast senders there are right now
+    pub(crate) fn senders(&self) -> usize {
+        self.senders.load(Ordering::Acquire)
+    }
+
+    /// Write a value into the slot for `id` returning the value it replaced if there was one.
+    /// The old value should be dropped after `id` has been published.
+    ///
+    /// # Safety
+    /// The caller must hold the claim on `id` and no reader can still be reading the slot
+    #[inline(always)]
+    pub(crate) unsafe fn write(&self, id: isize, value: T) -> Option<T> {
+        debug_assert!(id >= 0);
+        let index = (id as usize).pow_2_mod(self.capacity);
+        let slot = self.ring.add(index);
+        if id < self.capacity as isize {
+            // The slot has never been written to so there's nothing to drop
+            core::ptr::write(slot, value);
+            None
+        } else {
+            Some(core::ptr::replace(slot, value))
+        }
+    }
+
+    /// Write a value into the slot for `id` returning the value it replaced. The same as
+    /// [write](Self::write) for a slot that's known to have been written before.
+    ///
+    /// # Safety
+    /// The same as [write](Self::write) and `id` must be at least the capacity
+    #[inline(always)]
+    pub(crate) unsafe fn replace(&self, id: isize, value: T) -> T {
+        debug_assert!(id >= self.capacity as isize);
+        let index = (id as usize).pow_2_mod(self.capacity);
+        core::ptr::replace(self.ring.add(index), value)
+    }
+
+    /// Borrow the value in the slot for `id`
+    ///
+    /// # Safety
+    /// `id` must have been published and the caller must be holding it in the reader tracker for
+    /// as long as the reference is alive
+    #[inline(always)]
+    pub(crate) unsafe fn slot(&self, id: isize) -> &T {
+        debug_assert!(id >= 0);
+        let index = (id as usize).pow_2_mod(self.capacity);
+        &*self.ring.add(index)
+    }
+
+    /// Borrow the run of `count` slots starting at `from` as at most two slices. The second is
+    /// only used when the run wraps around the end of the ring
+    ///
+    /// # Safety
+    /// Every id in the run must have been published and the caller must be holding `from` in the
+    /// reader tracker for as long as the slices are alive
+    #[inline(always)]
+    pub(crate) unsafe fn slices(&self, from: isize, count: usize) -> (&[T], &[T]) {
+        debug_assert!(from >= 0);
+        debug_assert!(count <= self.capacity);
+        let start = (from as usize).pow_2_mod(self.capacity);
+        if start + count <= self.capacity {
+            (
+                core::slice::from_raw_parts(self.ring.add(start), count),
+                &[],
+            )
+        } else {
+            (
+                core::slice::from_raw_parts(self.ring.add(start), self.capacity - start),
+                core::slice::from_raw_parts(self.ring, start + count - self.capacity),
+            )
+        }
+    }
+
+    /// Mutably borrow the value in the slot for `id`
+    ///
+    /// # Safety
+    /// The same as [slot](Self::slot) and the caller must be the only reader of `id`
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn slot_mut(&self, id: isize) -> &mut T {
+        debug_assert!(id >= 0);
+        let index = (id as usize).pow_2_mod(self.capacity);
+        &mut *self.ring.add(index)
+    }
+
+    /// Clone the value out of the slot for `id`
+    ///
+    /// # Safety
+    /// `id` must have been published and the caller must be holding it in the reader tracker
+    #[inline(always)]
+    pub(crate) unsafe fn read(&self, id: isize) -> T
+    where
+        T: Clone,
+    {
+        self.slot(id).clone()
     }
 }
 
-impl<T> Core for Ring<T> {
+impl<T, ST, RT> Core for Ring<T, ST, RT>
+where
+    ST: ProducerTracker,
+    RT: ReceiverTracker,
+{
     type T = T;
-    type SendTracker = SequentialProducerTracker<SpinBlockWait>;
-    type ReadTracker = MultiCursorTracker<SpinBlockWait>;
+    type SendTracker = ST;
+    type ReadTracker = RT;
 
     fn sender_tracker(&self) -> &Self::SendTracker {
         &self.sender_tracker
@@ -119,10 +581,6 @@ impl<T> Core for Ring<T> {
         &self.reader_tracker
     }
 
-    fn ring(&self) -> *mut Vec<Self::T> {
-        self.ring
-    }
-
     fn capacity(&self) -> usize {
         self.capacity
     }
@@ -141,6 +599,34 @@ impl<T> ChannelHandles<T> {
     pub fn dissolve(self) -> (BroadcastSender<T>, BroadcastReceiver<T>) {
         (self.sender, self.receiver)
     }
+
+    /// A readable report on the state of the channel for working out why it's stuck. See
+    /// [ChannelStats::diagnose]
+    pub fn diagnose(&self) -> alloc::string::String {
+        self.sender.stats().diagnose()
+    }
+
+    /// Empty the channel and put the sender and receiver back to where they started so the
+    /// channel can be used again without allocating. Useful in benchmarks. Everything still in
+    /// the channel is dropped.
+    ///
+    /// # Panics
+    /// If there are any other handles to the channel. That includes clones of the sender or
+    /// receiver, [stats](ChannelStats) and [factories](receiver::ReceiverFactory)
+    pub fn recycle(&mut self) {
+        let core = self.sender.get_core();
+        // This one, the sender and the receiver
+        assert_eq!(
+            Arc::strong_count(&core),
+            3,
+            "can't recycle a channel that's still in use somewhere else"
+        );
+        self.receiver.detach();
+        // Nothing else can get to the ring and the receiver has left it
+        unsafe { core.recycle() };
+        self.sender.recycle();
+        self.receiver.recycle();
+    }
 }
 
 ///Creates a new mpmc broadcast channel returning both a sender and receiver
@@ -148,15 +634,133 @@ pub fn channel<T>(size: usize) -> Result<ChannelHandles<T>, ChannelError> {
     channel_with(size)
 }
 
+/// Creates a channel that buffers at most `max_bytes` of messages rather than a number of them.
+///
+/// Every slot in the ring is `size_of::<T>()` bytes so this is the same as a channel with as
+/// many slots as fit in `max_bytes`, rounded down to a power of 2. Senders block once that many
+/// messages are waiting. It only counts the message itself so anything a message points to, like
+/// the contents of a `Vec` or `String`, isn't part of the budget. Zero sized messages are
+/// counted as a byte each so the budget still bounds the channel. Fails with
+/// [ChannelError::BudgetTooSmall] if not even one message fits
+pub fn channel_bytes<T>(max_bytes: usize) -> Result<ChannelHandles<T>, ChannelError> {
+    // Zero sized messages would otherwise all fit in any budget
+    let fits = max_bytes / core::mem::size_of::<T>().max(1);
+    if fits == 0 {
+        return Err(ChannelError::BudgetTooSmall);
+    }
+    // The largest power of 2 that's no bigger than what fits
+    channel_with(1 << (usize::BITS - 1 - fits.leading_zeros()))
+}
+
 pub fn busy_channel<T>(size: usize) -> Result<ChannelHandles<T>, ChannelError> {
     channel_with(size)
 }
 
 pub fn channel_with<T>(size: usize) -> Result<ChannelHandles<T>, ChannelError> {
+    ChannelBuilder::new(size).build()
+}
+
+/// The same as [channel] but `size` must already be a power of 2. Fails with
+/// [ChannelError::InvalidSize] rather than rounding it up
+pub fn channel_exact<T>(size: usize) -> Result<ChannelHandles<T>, ChannelError> {
+    if !size.is_power_of_two() {
+        return Err(ChannelError::InvalidSize);
+    }
+    channel_with(size)
+}
+
+/// Fails to build if `N` isn't a valid buffer size
+struct CheckedSize<const N: usize>;
+
+impl<const N: usize> CheckedSize<N> {
+    const VALID: () = assert!(
+        N.is_power_of_two() && N <= isize::MAX as usize,
+        "the buffer size must be a power of 2"
+    );
+}
+
+/// Creates a new mpmc broadcast channel with a buffer size that's checked at compile time.
+/// Unlike [channel] the size isn't rounded up so it must already be a power of 2
+///
+/// ```
+/// let (mut sender, mut receiver) = nexusq::sized_channel::<usize, 16>().dissolve();
+/// sender.send(1);
+/// assert_eq!(receiver.recv(), 1);
+/// ```
+///
+/// ```compile_fail,E0080
+/// let handles = nexusq::sized_channel::<usize, 100>();
+/// ```
+pub fn sized_channel<T, const N: usize>() -> ChannelHandles<T> {
+    #[allow(clippy::let_unit_value)]
+    let () = CheckedSize::<N>::VALID;
+    channel_with(N).expect("the buffer size is checked at compile time")
+}
+
+/// Creates a new mpmc broadcast channel that carries references rather than values. Nothing is
+/// cloned other than the reference so `T` doesn't need to be `Clone`.
+/// The channel can't outlive the data it's borrowing which makes it a good fit for scoped threads
+///
+/// ```
+/// let data = vec![String::from("hello"), String::from("world")];
+/// let (mut sender, mut receiver) = nexusq::channel_ref(4).expect("couldn't create channel").dissolve();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| {
+///         for value in &data {
+///             sender.send(value);
+///         }
+///     });
+///     assert_eq!(receiver.recv(), "hello");
+///     assert_eq!(receiver.recv(), "world");
+/// });
+/// ```
+///
+/// ```compile_fail,E0597
+/// let handles = {
+///     let data = String::from("hello");
+///     let mut handles = nexusq::channel_ref(4).expect("couldn't create channel");
+///     handles.sender.send(&data);
+///     handles
+/// };
+/// ```
+pub fn channel_ref<'a, T>(size: usize) -> Result<ChannelHandles<&'a T>, ChannelError>
+where
+    T: Sync + ?Sized,
+{
+    channel_with(size)
+}
+
+/// Creates a channel for broadcasting values that are expensive to clone. Each value is put in
+/// an [Arc] once when it's sent with [send_shared](BroadcastSender::send_shared) and receivers
+/// get a clone of the [Arc] instead of the whole value
+///
+/// ```
+/// let (mut sender, mut receiver) = nexusq::channel_shared::<Vec<u8>>(4)
+///     .expect("couldn't create channel")
+///     .dissolve();
+/// let mut other = receiver.clone();
+/// sender.send_shared(vec![0; 1024]);
+/// let first = receiver.recv();
+/// let second = other.recv();
+/// // Both receivers got the same value
+/// assert!(std::sync::Arc::ptr_eq(&first, &second));
+/// ```
+pub fn channel_shared<T>(size: usize) -> Result<ChannelHandles<Arc<T>>, ChannelError> {
+    channel_with(size)
+}
+
+/// Creates a new mpmc broadcast channel returning only a sender. No receiver means nothing is
+/// holding back the senders until one is created with [BroadcastSender::add_stream]
+pub fn sender_only<T>(size: usize) -> Result<BroadcastSender<T>, ChannelError> {
     let core = Arc::new(Ring::<T>::new(size)?);
-    let sender = sender::BroadcastSender::from(core.clone());
-    let receiver = receiver::BroadcastReceiver::try_from(core)?;
-    Ok(ChannelHandles::new(sender, receiver))
+    Ok(sender::BroadcastSender::from(core))
+}
+
+/// Creates a new mpmc broadcast channel returning only a receiver. Senders can be created from
+/// the receiver later on
+pub fn receiver_only<T>(size: usize) -> Result<BroadcastReceiver<T>, ChannelError> {
+    let core = Arc::new(Ring::<T>::new(size)?);
+    Ok(receiver::BroadcastReceiver::try_from(core)?)
 }
 
 #[cfg(test)]
@@ -189,6 +793,8 @@ mod tests {
         results
     }
 
+    // `is_multiple_of` would need rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
     fn jitter_sleep(sleep_time: Duration, seed: usize, jtter_duration: Duration, i: usize) {
         if !sleep_time.is_zero() {
             if i % seed == 0 {
@@ -430,4 +1036,204 @@ mod tests {
             Duration::from_micros(20),
         );
     }
+
+    #[test]
+    fn receiver_churn() {
+        let num = 20_000;
+        let num_writers = 2;
+        let ChannelHandles { sender, receiver } =
+            channel::<(usize, usize)>(16).expect("couldn't create channel");
+        // Something for the churning threads to add streams from that doesn't hold up the senders
+        let mut template = receiver.clone();
+        template.detach();
+        let done = Arc::new(AtomicBool::new(false));
+        let churners: Vec<_> = (0..8)
+            .map(|_| {
+                let template = template.clone();
+                let done = done.clone();
+                spawn(move || {
+                    let mut streams = 0;
+                    while !done.load(Ordering::Acquire) {
+                        let mut receiver = template.add_stream().expect("couldn't add stream");
+                        let mut last = [None; 2];
+                        let mut read = 0;
+                        while read < 3 && !done.load(Ordering::Acquire) {
+                            let Ok((writer, i)) = receiver.try_recv() else {
+                                std::thread::yield_now();
+                                continue;
+                            };
+                            // Reading a slot that had been overwritten would show up as a
+                            // message arriving out of order
+                            assert!(last[writer] < Some(i), "{writer} sent {i} after {last:?}");
+                            last[writer] = Some(i);
+                            read += 1;
+                        }
+                        streams += 1;
+                    }
+                    streams
+                })
+            })
+            .collect();
+        let writers: Vec<_> = (0..num_writers)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                spawn(move || {
+                    for i in 0..num {
+                        sender.send((writer, i));
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let reader = spawn(move || {
+            let mut receiver = receiver;
+            let mut next = [0; 2];
+            for _ in 0..num * num_writers {
+                let (writer, i) = receiver.recv();
+                assert_eq!(next[writer], i, "the long lived receiver lost a message");
+                next[writer] += 1;
+            }
+        });
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        reader.join().expect("reader panicked");
+        done.store(true, Ordering::Release);
+        let streams: usize = churners
+            .into_iter()
+            .map(|churner| churner.join().expect("churner panicked"))
+            .sum();
+        assert!(streams > 0);
+    }
+
+    #[test]
+    fn channel_bytes() {
+        assert!(matches!(
+            super::channel_bytes::<u64>(7),
+            Err(ChannelError::BudgetTooSmall)
+        ));
+        for (max_bytes, capacity) in [(8, 1), (64, 8), (100, 8), (128, 16)] {
+            let (sender, _) = super::channel_bytes::<u64>(max_bytes)
+                .expect("couldn't create channel")
+                .dissolve();
+            assert_eq!(sender.get_core().capacity(), capacity);
+        }
+        // Zero sized messages count as a byte each
+        let (sender, _) = super::channel_bytes::<()>(5)
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(sender.get_core().capacity(), 4);
+        assert!(matches!(
+            super::channel_bytes::<()>(0),
+            Err(ChannelError::BudgetTooSmall)
+        ));
+    }
+
+    #[test]
+    fn channel_exact() {
+        for size in [0, 3, 7, 1000] {
+            assert!(matches!(
+                super::channel_exact::<usize>(size),
+                Err(ChannelError::InvalidSize)
+            ));
+        }
+        let (sender, _) = super::channel_exact::<usize>(8)
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(sender.get_core().capacity(), 8);
+        let (sender, _) = channel::<usize>(7)
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(sender.get_core().capacity(), 8);
+    }
+
+    #[test]
+    fn fan_out_fairness() {
+        const NUM_WRITERS: usize = 2;
+        const NUM_TO_WRITE: usize = 300;
+        const CAPACITY: isize = 8;
+
+        let (sender, receiver) = channel::<(usize, usize)>(CAPACITY as usize)
+            .expect("couldn't create channel")
+            .dissolve();
+        // A receiver that keeps up, one that's a bit slower and one that's much slower
+        let readers: Vec<_> = [0, 20, 200]
+            .into_iter()
+            .map(|sleep_micros| {
+                let mut receiver = receiver.clone();
+                spawn(move || {
+                    (0..NUM_WRITERS * NUM_TO_WRITE)
+                        .map(|_| {
+                            std::thread::sleep(Duration::from_micros(sleep_micros));
+                            receiver.recv()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        drop(receiver);
+        let writers: Vec<_> = (0..NUM_WRITERS)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                spawn(move || {
+                    let core = sender.get_core();
+                    for i in 0..NUM_TO_WRITE {
+                        sender.send((writer, i));
+                        // The tail only moves forward so loading it second can't make this fail
+                        let published = core.sender_tracker().current();
+                        let tail = core.reader_tracker().current();
+                        assert!(
+                            published < tail + CAPACITY,
+                            "published {published} with the slowest reader at {tail}"
+                        );
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        let received: Vec<_> = readers
+            .into_iter()
+            .map(|reader| reader.join().expect("reader panicked"))
+            .collect();
+        // Every receiver got everything, in the same order, whatever speed it read at
+        assert!(received.iter().all(|messages| *messages == received[0]));
+        let mut next = [0; NUM_WRITERS];
+        for (writer, i) in &received[0] {
+            assert_eq!(next[*writer], *i);
+            next[*writer] += 1;
+        }
+        assert_eq!(next, [NUM_TO_WRITE; NUM_WRITERS]);
+    }
+
+    #[test]
+    fn recycle() {
+        let value = std::sync::Arc::new(());
+        let mut handles = channel::<std::sync::Arc<()>>(4).expect("couldn't create channel");
+        for _ in 0..6 {
+            handles.sender.send(value.clone());
+            handles.receiver.recv();
+        }
+        assert_eq!(std::sync::Arc::strong_count(&value), 5);
+        handles.recycle();
+        assert_eq!(std::sync::Arc::strong_count(&value), 1);
+        // Starts again from the beginning
+        assert_eq!(handles.sender.next_sequence(), Sequence(0));
+        for _ in 0..6 {
+            handles.sender.send(value.clone());
+            handles.receiver.recv();
+        }
+        drop(handles);
+        assert_eq!(std::sync::Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "still in use")]
+    fn recycle_in_use() {
+        let mut handles = channel::<usize>(4).expect("couldn't create channel");
+        let _other = handles.receiver.clone();
+        handles.recycle();
+    }
 }
@@ -0,0 +1,217 @@
+//! A multi producer single consumer channel that moves values rather than broadcasting them.
+//!
+//! With only one receiver there's no need for everyone to get a copy so [MpscReceiver::recv]
+//! takes the value out of the ring instead of cloning it and `T` doesn't need to be `Clone`.
+//! The receiver can't be cloned and nothing can create another one.
+
+use alloc::sync::Arc;
+use std::sync::atomic::{fence, Ordering};
+
+use super::tracker::{
+    ProducerTracker, ReceiverTracker, SequentialProducerTracker, SingleCursorTracker, Tracker,
+};
+use super::wait_strategy::SpinBlockWait;
+use super::{checked_buffer_size, ChannelError, Core, Ring};
+
+type MpscRing<T> =
+    Ring<Option<T>, SequentialProducerTracker<SpinBlockWait>, SingleCursorTracker<SpinBlockWait>>;
+
+/// Creates a new mpsc channel returning a sender and the only receiver it will ever have
+pub fn channel<T>(size: usize) -> Result<(MpscSender<T>, MpscReceiver<T>), ChannelError> {
+    let buffer_size = checked_buffer_size(size)?;
+    let core = Arc::new(MpscRing::with_trackers(
+        buffer_size,
+        SequentialProducerTracker::new(SpinBlockWait::new(0, 0)),
+        SingleCursorTracker::new(SpinBlockWait::new(0, 0)),
+    ));
+    core.reader_tracker().register(0)?;
+    let sender = MpscSender {
+        core: core.clone(),
+        capacity: buffer_size as isize,
+        cached_tail: 0,
+    };
+    let receiver = MpscReceiver {
+        core,
+        internal_cursor: -1,
+        committed_cache: -1,
+    };
+    Ok((sender, receiver))
+}
+
+// Values are only ever moved to the receiver, never shared, so `T` doesn't need to be `Sync`
+unsafe impl<T: Send> Send for MpscSender<T> {}
+
+#[derive(Debug)]
+pub struct MpscSender<T> {
+    core: Arc<MpscRing<T>>,
+    capacity: isize,
+    cached_tail: isize,
+}
+
+impl<T> Clone for MpscSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            core: self.core.clone(),
+            capacity: self.capacity,
+            cached_tail: 0,
+        }
+    }
+}
+
+impl<T> MpscSender<T> {
+    /// Send a single value to the channel. This function will block if there is no space
+    /// available in the channel.
+    pub fn send(&mut self, value: T) {
+        let claimed_id = self.core.sender_tracker().make_claim();
+        let tail = claimed_id - self.capacity;
+        if tail >= 0 && self.cached_tail <= tail {
+            let reader_tail = self.core.reader_tracker().wait_for(tail + 1);
+            // Once the receiver has gone nothing stops two senders a lap apart writing the same
+            // slot. Wait for the one with the slot's last message to finish with it
+            let published = self.core.sender_tracker().wait_published(tail);
+            self.cached_tail = reader_tail.min(published + 1);
+        }
+        debug_assert!(tail < 0 || self.cached_tail > tail);
+
+        let old_value;
+        unsafe {
+            old_value = self.core.write(claimed_id, Some(value));
+            fence(Ordering::Release)
+        }
+        self.core.sender_tracker().publish(claimed_id);
+        // Only holds something if the receiver was dropped before it could take it
+        drop(old_value);
+    }
+}
+
+unsafe impl<T: Send> Send for MpscReceiver<T> {}
+
+#[derive(Debug)]
+pub struct MpscReceiver<T> {
+    core: Arc<MpscRing<T>>,
+    internal_cursor: isize,
+    committed_cache: isize,
+}
+
+impl<T> Drop for MpscReceiver<T> {
+    fn drop(&mut self) {
+        self.core
+            .reader_tracker()
+            .de_register(self.internal_cursor.clamp(0, isize::MAX));
+    }
+}
+
+impl<T> MpscReceiver<T> {
+    /// Take the next value out of the channel. This function will block and wait for data to
+    /// become available.
+    pub fn recv(&mut self) -> T {
+        self.internal_cursor += 1;
+        if self.committed_cache < self.internal_cursor {
+            self.committed_cache = self.core.sender_tracker().wait_for(self.internal_cursor);
+        }
+        if self.internal_cursor > 0 {
+            self.core
+                .reader_tracker()
+                .update(self.internal_cursor - 1, self.internal_cursor);
+        }
+        debug_assert!(self.committed_cache >= self.internal_cursor);
+        fence(Ordering::Acquire);
+        // This is the only receiver so nobody else can be looking at the slot
+        unsafe { self.core.slot_mut(self.internal_cursor) }
+            .take()
+            .expect("published slot was empty")
+    }
+}
+
+#[cfg(test)]
+mod mpsc_tests {
+    use super::*;
+
+    // Deliberately not Clone
+    #[derive(Debug, PartialEq)]
+    struct Moved(usize);
+
+    #[test]
+    fn send_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        for round in 0..10 {
+            for i in 0..3 {
+                sender.send(Moved(round * 3 + i));
+            }
+            for i in 0..3 {
+                assert_eq!(receiver.recv(), Moved(round * 3 + i));
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_moved_out() {
+        let value = Arc::new(());
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        sender.send(value.clone());
+        let received = receiver.recv();
+        // Only the original and the one that was received. Nothing is left behind in the ring
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(received);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel");
+        drop(receiver);
+        for i in 0..20 {
+            sender.send(Moved(i));
+        }
+    }
+
+    #[test]
+    fn receiver_dropped_senders_lapping() {
+        let value = Arc::new(());
+        let (sender, receiver) = channel(4).expect("couldn't create channel");
+        drop(receiver);
+        let senders: Vec<_> = (0..4)
+            .map(|_| {
+                let mut sender = sender.clone();
+                let value = value.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5000 {
+                        sender.send(value.clone());
+                    }
+                })
+            })
+            .collect();
+        for handle in senders {
+            handle.join().expect("sender panicked");
+        }
+        drop(sender);
+        // Every value that was overwritten was dropped exactly once
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn two_writers() {
+        let num = 500;
+        let (sender, mut receiver) = channel(8).expect("couldn't create channel");
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num {
+                        sender.send((writer, Moved(i)));
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut next = [0; 2];
+        for _ in 0..num * 2 {
+            let (writer, Moved(i)) = receiver.recv();
+            assert_eq!(next[writer], i);
+            next[writer] += 1;
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+    }
+}
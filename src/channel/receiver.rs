@@ -1,13 +1,18 @@
 use thiserror::Error as ThisError;
 
+use alloc::borrow::Cow;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use std::sync::atomic::{fence, Ordering};
+use std::time::{Duration, Instant};
 
+use super::registry::ReceiverState;
 use super::tracker::{ReceiverTracker, Tracker, TrackerError};
-use super::Core;
+use super::wait_strategy::WaitStrategy;
+use super::{Core, Sequence};
 use crate::channel::Ring;
-use crate::utils::FastMod;
-use crate::BroadcastSender;
+use crate::utils::validate_ordering;
+use crate::{BroadcastSender, ChannelStats};
 
 #[derive(Debug, ThisError)]
 pub enum ReceiverError {
@@ -15,6 +20,32 @@ pub enum ReceiverError {
     NoNewData,
     #[error("failed to register the receiver on the channel. Generally a result of the channel being entirely overwritten too quickly")]
     RegistrationFailed(#[from] TrackerError),
+    #[error("the receiver was evicted by a sender for holding up the channel")]
+    Evicted,
+    #[error("the requested position is no longer in the channel")]
+    Lagged,
+    #[error("every sender has gone, or the channel has been shut down, and everything in it has been read")]
+    Disconnected,
+    #[error("nothing was sent before the timeout")]
+    Timeout,
+}
+
+/// A sender moved past messages the receiver hadn't read yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ThisError)]
+#[error("the receiver fell behind and {lost} messages were lost")]
+pub struct OverrunError {
+    /// How many messages were skipped
+    pub lost: usize,
+}
+
+/// What to do when creating a receiver on a channel that nothing has been sent on yet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegisterPolicy {
+    /// Create the receiver anyway. It waits for the first message
+    #[default]
+    BlockForFuture,
+    /// Fail with [ReceiverError::NoNewData] instead. Useful for probes that shouldn't wait
+    ErrorIfEmpty,
 }
 
 pub trait Receiver<T>: Clone {
@@ -25,15 +56,41 @@ pub trait Receiver<T>: Clone {
 pub struct BroadcastReceiver<T> {
     core: Arc<Ring<T>>,
     internal_cursor: isize,
+    // The position this receiver holds in the reader tracker. None when detached
+    shared_cursor: Option<isize>,
     capacity: isize,
     committed_cache: isize,
+    state: Arc<ReceiverState>,
+    // Left busy by recv_borrowed so that it can't be evicted while the value is borrowed
+    borrowed: bool,
+    // Reads don't move the shared cursor, only ack_through does
+    deferred_acks: bool,
+    // The receiver hasn't read the value at its shared cursor yet
+    holding_unread: bool,
+}
+
+/// Ends a read that panics part way through so that the receiver isn't left busy and a sender
+/// trying to evict it doesn't wait forever. Forgotten once the read has succeeded
+struct EndOnUnwind<'a>(Option<&'a ReceiverState>);
+
+impl<'a> EndOnUnwind<'a> {
+    #[inline(always)]
+    fn new<T>(receiver: &'a BroadcastReceiver<T>) -> Self {
+        Self(receiver.core.evictable().then_some(&*receiver.state))
+    }
+}
+
+impl Drop for EndOnUnwind<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = self.0 {
+            state.end();
+        }
+    }
 }
 
 impl<T> Drop for BroadcastReceiver<T> {
     fn drop(&mut self) {
-        self.core
-            .reader_tracker()
-            .de_register(self.internal_cursor.clamp(0, isize::MAX));
+        self.detach();
     }
 }
 
@@ -41,21 +98,52 @@ impl<T> TryFrom<Arc<Ring<T>>> for BroadcastReceiver<T> {
     type Error = ReceiverError;
 
     fn try_from(core: Arc<Ring<T>>) -> Result<Self, Self::Error> {
-        let committed = core.sender_tracker().current();
+        let mut retries = core.register_retries();
+        let (committed, shared_cursor) = loop {
+            let committed = core.sender_tracker().current();
+            match core
+                .reader_tracker()
+                .register(committed.clamp(0, isize::MAX))
+            {
+                Ok(at) => break (committed, at),
+                // The other readers have all moved past what we thought was the latest
+                Err(TrackerError::PositionTooOld) if retries > 0 => {
+                    retries -= 1;
+                    core.register_backoff();
+                }
+                Err(error) => return Err(error.into()),
+            }
+        };
         let internal_cursor = committed.clamp(0, isize::MAX) - 1;
-        core.reader_tracker()
-            .register(committed.clamp(0, isize::MAX))?;
+        let holding_unread = internal_cursor < shared_cursor;
+        let state = core.receivers().new_receiver(shared_cursor, holding_unread);
 
         let capacity = core.capacity() as isize;
         Ok(Self {
             core,
             internal_cursor,
+            shared_cursor: Some(shared_cursor),
             capacity,
             committed_cache: committed,
+            borrowed: false,
+            deferred_acks: false,
+            holding_unread,
+            state,
         })
     }
 }
 
+impl<T> BroadcastReceiver<T> {
+    /// Create a receiver at the most recent entry in the stream following `policy` if nothing
+    /// has been sent yet
+    pub(crate) fn join(core: Arc<Ring<T>>, policy: RegisterPolicy) -> Result<Self, ReceiverError> {
+        if policy == RegisterPolicy::ErrorIfEmpty && core.sender_tracker().current() < 0 {
+            return Err(ReceiverError::NoNewData);
+        }
+        core.try_into()
+    }
+}
+
 impl<T> TryFrom<BroadcastSender<T>> for BroadcastReceiver<T> {
     type Error = ReceiverError;
 
@@ -65,17 +153,32 @@ impl<T> TryFrom<BroadcastSender<T>> for BroadcastReceiver<T> {
 }
 
 impl<T> Clone for BroadcastReceiver<T> {
-    /// Creates a new receiver at the same point in the stream
+    /// Creates a new receiver at the same point in the stream. Cloning a detached receiver
+    /// creates another detached receiver
     fn clone(&self) -> Self {
-        self.core
-            .reader_tracker()
-            .register(self.internal_cursor)
-            .expect("couldn't register receiver during clone");
+        let attached = self.hold(|at| {
+            let at = self
+                .core
+                .reader_tracker()
+                .register(at)
+                .expect("couldn't register receiver during clone");
+            let unread = self.internal_cursor < at;
+            (at, self.core.receivers().new_receiver(at, unread))
+        });
+        let (shared_cursor, state) = match attached {
+            Some((at, state)) => (Some(at), state),
+            None => (None, self.core.receivers().new_detached()),
+        };
         Self {
             core: self.core.clone(),
             internal_cursor: self.internal_cursor,
+            shared_cursor,
             capacity: self.capacity,
             committed_cache: self.committed_cache,
+            borrowed: false,
+            deferred_acks: self.deferred_acks,
+            holding_unread: matches!(shared_cursor, Some(at) if self.internal_cursor < at),
+            state,
         }
     }
 }
@@ -86,18 +189,556 @@ impl<T> BroadcastReceiver<T> {
         self.internal_cursor += 1;
     }
     #[inline(always)]
-    fn publish_position(&self) {
-        self.core
-            .reader_tracker()
-            .update(self.internal_cursor - 1, self.internal_cursor)
+    fn publish_position(&mut self) {
+        if !self.deferred_acks {
+            self.publish_through(self.internal_cursor);
+        }
+    }
+    #[inline(always)]
+    fn publish_through(&mut self, to: isize) {
+        if let Some(shared_cursor) = self.shared_cursor {
+            if shared_cursor < to {
+                // Before the tracker wakes anyone waiting for the channel to drain
+                self.state.set_position(to);
+                self.core.reader_tracker().update(shared_cursor, to);
+                self.shared_cursor = Some(to);
+            }
+            if self.holding_unread {
+                // Only happens on the first read after registering. Moving onto the slot we were
+                // already holding doesn't move the tail so nothing else would wake a sender
+                // waiting for the channel to drain. Waking senders that are waiting for space
+                // for nothing is expensive so only do it if there's someone to wake
+                self.holding_unread = false;
+                self.state.set_unread(false);
+                if self.core.draining() {
+                    self.core.reader_tracker().wait_strategy().notify();
+                }
+            }
+        }
+    }
+    /// Mark the receiver as busy so a sender can't evict it while it's using its position.
+    /// Returns false if it's already been evicted
+    #[inline(always)]
+    fn begin(&self) -> bool {
+        !self.core.evictable() || self.state.begin()
+    }
+    #[inline(always)]
+    fn end(&self) {
+        if self.core.evictable() {
+            self.state.end();
+        }
+    }
+    /// Run `f` with the position this receiver holds in the reader tracker. The receiver can't be
+    /// evicted part way through. Returns None if the receiver is detached or has been evicted
+    #[inline(always)]
+    fn hold<U>(&self, f: impl FnOnce(isize) -> U) -> Option<U> {
+        let shared_cursor = self.shared_cursor?;
+        if !self.begin() {
+            return None;
+        }
+        let result = f(shared_cursor);
+        self.end();
+        Some(result)
+    }
+    /// Let the receiver be evicted again after [recv_borrowed](Self::recv_borrowed)
+    #[inline(always)]
+    fn release_borrow(&mut self) {
+        if self.borrowed {
+            self.borrowed = false;
+            self.state.release();
+        }
+    }
+    /// Stop reads from letting the senders have slots back. The receiver holds on to everything
+    /// from the last position it [acknowledged](Self::ack_through) so a batch can be read and
+    /// its progress committed once with a single update to the reader tracker. The senders block
+    /// once the ring fills up so acknowledge at least every capacity messages.
+    ///
+    /// Turning it back off doesn't acknowledge anything by itself. The next read does
+    pub fn set_deferred_acks(&mut self, deferred: bool) {
+        self.deferred_acks = deferred;
+    }
+    /// Let the senders have back every slot before `seq`. This is only useful with
+    /// [deferred acks](Self::set_deferred_acks) as reads acknowledge themselves otherwise.
+    /// The receiver keeps holding `seq` itself, the same as it holds the last message it read.
+    ///
+    /// Does nothing if the receiver is detached. Fails with [ReceiverError::Evicted] if a sender
+    /// has evicted the receiver.
+    ///
+    /// # Panics
+    /// If `seq` is before the last acknowledged position or hasn't been read yet
+    pub fn ack_through(&mut self, seq: Sequence) -> Result<(), ReceiverError> {
+        let Some(acked) = self.shared_cursor else {
+            return Ok(());
+        };
+        let seq = seq.0.min(isize::MAX as u64) as isize;
+        assert!(
+            acked <= seq && seq <= self.internal_cursor,
+            "can only acknowledge from {acked} to {} but got {seq}",
+            self.internal_cursor
+        );
+        if !self.begin() {
+            self.shared_cursor = None;
+            return Err(ReceiverError::Evicted);
+        }
+        self.publish_through(seq);
+        self.end();
+        Ok(())
+    }
+    /// Let the senders send `n` more messages. Only matters on a channel built with
+    /// [credit](crate::ChannelBuilder::credit) set, where senders wait for every receiver
+    /// to grant credit for a message before sending it. A new receiver, including a clone,
+    /// starts without any
+    pub fn grant_credit(&mut self, n: usize) {
+        self.state.grant_credit(n.min(isize::MAX as usize) as isize);
+        if self.core.credited() {
+            self.core.reader_tracker().wait_strategy().notify();
+        }
     }
     /// Creates a new receiver at the most recent entry in the stream
     pub fn add_stream(&self) -> Result<Self, ReceiverError> {
         self.core.clone().try_into()
     }
+    /// [add_stream](Self::add_stream) choosing what happens if nothing has been sent yet
+    pub fn add_stream_with(&self, policy: RegisterPolicy) -> Result<Self, ReceiverError> {
+        Self::join(self.core.clone(), policy)
+    }
+    /// Stop holding back the producers. Use this when the receiver won't be read from for a
+    /// while. Senders are free to overwrite anything this receiver hasn't read yet.
+    /// The next [recv](Self::recv) will [reattach](Self::reattach) the receiver.
+    pub fn detach(&mut self) {
+        self.release_borrow();
+        if let Some(shared_cursor) = self.shared_cursor.take() {
+            // There's nothing to do if a sender has already evicted us
+            if self.begin() {
+                self.core.receivers().remove(self.state.id());
+                self.core.reader_tracker().de_register(shared_cursor);
+                self.state.end_detached();
+            }
+        }
+    }
+    /// Re-register a detached receiver at the most recent entry in the stream. Everything
+    /// sent while the receiver was detached is skipped. Returns how many messages were skipped.
+    /// Does nothing if the receiver is already attached.
+    pub fn reattach(&mut self) -> Result<usize, ReceiverError> {
+        if self.shared_cursor.is_some() {
+            return Ok(0);
+        }
+        let shared_cursor = loop {
+            let committed = self.core.sender_tracker().current().clamp(0, isize::MAX);
+            match self.core.reader_tracker().register(committed) {
+                Ok(at) => break at,
+                // The other readers have all moved past what we thought was the latest
+                Err(TrackerError::PositionTooOld) => continue,
+                Err(error) => return Err(error.into()),
+            }
+        };
+        let next_cursor = (shared_cursor - 1).max(self.internal_cursor);
+        self.holding_unread = next_cursor < shared_cursor;
+        // Credit granted while detached still counts
+        let credit = self.state.credit().max(next_cursor + 1);
+        self.state = self.core.receivers().rejoin(
+            self.state.id(),
+            shared_cursor,
+            self.holding_unread,
+            credit,
+        );
+        let missed = next_cursor - self.internal_cursor;
+        self.internal_cursor = next_cursor;
+        self.shared_cursor = Some(shared_cursor);
+        Ok(missed as usize)
+    }
+    /// Jump so that the next message read is `to`, skipping everything in between. Returns how
+    /// many messages were skipped.
+    ///
+    /// `to` is clamped so that it's no further than the next message to be sent. An attached
+    /// receiver can't move backwards so it's also clamped to the next message the receiver would
+    /// have read. A [detached](Self::detach) receiver is reattached at `to`. That fails with
+    /// [ReceiverError::Lagged] if `to` has already been overwritten.
+    ///
+    /// Nothing can be held a whole lap ahead of the slowest receiver so when the ring is full
+    /// and another receiver is holding its oldest message this stops one short of the next
+    /// message to be sent
+    pub fn fast_forward(&mut self, to: Sequence) -> Result<usize, ReceiverError> {
+        self.release_borrow();
+        let next = self.internal_cursor + 1;
+        let head = self.core.sender_tracker().current() + 1;
+        let mut to = (to.0.min(isize::MAX as u64) as isize).min(head);
+        if !self.is_detached() {
+            to = to.max(next);
+        }
+        match self.move_to(to) {
+            Err(ReceiverError::RegistrationFailed(TrackerError::PositionAliased)) => {
+                // The ring is full. If this receiver is the one holding the tail, moving to one
+                // short first lets the tail move on
+                self.move_to(to - 1)?;
+                match self.move_to(to) {
+                    Err(ReceiverError::RegistrationFailed(TrackerError::PositionAliased)) => {
+                        to -= 1
+                    }
+                    result => result?,
+                }
+            }
+            result => result?,
+        }
+        Ok((to - next).max(0) as usize)
+    }
+    /// Move back so that the last `n` messages are read again. Returns how many messages the
+    /// receiver actually moved back.
+    ///
+    /// The receiver can't go back past the oldest message still in the channel, so it moves
+    /// back less than `n` if some of them have already been overwritten. A
+    /// [detached](Self::detach) receiver is reattached
+    pub fn rewind(&mut self, n: usize) -> Result<usize, ReceiverError> {
+        self.release_borrow();
+        let next = self.internal_cursor + 1;
+        let mut tried = None;
+        loop {
+            let head = self.core.sender_tracker().current() + 1;
+            let oldest = self
+                .core
+                .reader_tracker()
+                .current()
+                .max(head - self.capacity)
+                .max(0);
+            let to = next
+                .saturating_sub(n.min(isize::MAX as usize) as isize)
+                .max(oldest);
+            if to >= next && !self.is_detached() {
+                return Ok(0);
+            }
+            match self.move_to(to) {
+                Ok(()) => return Ok((next - to).max(0) as usize),
+                // Another receiver moved the tail on. Try again from the new one
+                Err(ReceiverError::Lagged) if tried != Some(to) => tried = Some(to),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    /// Move this receiver so that the next message it reads is `to`. The new position is held
+    /// before the old one is let go so the tail can't jump past another receiver in between
+    fn move_to(&mut self, to: isize) -> Result<(), ReceiverError> {
+        let attached = self.shared_cursor.is_some();
+        if attached && !self.begin() {
+            self.shared_cursor = None;
+            return Err(ReceiverError::Evicted);
+        }
+        let at = match self.core.reader_tracker().register(to) {
+            Ok(at) if at == to => at,
+            Ok(at) => {
+                // We were the only receiver and the senders have already overwritten `to`
+                self.core.reader_tracker().de_register(at);
+                return Err(ReceiverError::Lagged);
+            }
+            Err(error) => {
+                if attached {
+                    self.end();
+                }
+                return Err(match error {
+                    TrackerError::PositionTooOld => ReceiverError::Lagged,
+                    error => error.into(),
+                });
+            }
+        };
+        if let Some(shared_cursor) = self.shared_cursor {
+            self.core.reader_tracker().de_register(shared_cursor);
+            self.state.set_position(at);
+            self.state.set_unread(true);
+            self.end();
+        } else {
+            let credit = self.state.credit().max(at);
+            self.state = self
+                .core
+                .receivers()
+                .rejoin(self.state.id(), at, true, credit);
+        }
+        self.holding_unread = true;
+        self.internal_cursor = at - 1;
+        self.shared_cursor = Some(at);
+        Ok(())
+    }
+    /// How much of the channel is waiting for this receiver to read from 0.0 to 1.0. It's 1.0
+    /// when the receiver is a whole channel behind or more, which a
+    /// [detached](Self::detach) receiver can be
+    pub fn backlog(&self) -> f32 {
+        let waiting = self.core.sender_tracker().current() - self.internal_cursor;
+        (waiting.clamp(0, self.capacity) as f32 / self.capacity as f32).min(1.0)
+    }
+    /// How many messages have been sent that this receiver hasn't read yet. Senders can publish
+    /// more at any time so it's only a snapshot, but it can only shrink when this receiver reads.
+    /// A [detached](Self::detach) receiver can be further behind than the capacity
+    pub fn len(&self) -> usize {
+        (self.core.sender_tracker().current() - self.internal_cursor).max(0) as usize
+    }
+    /// Has this receiver read everything that's been sent. Like [len](Self::len) this is racy
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// How many of the messages this receiver hasn't read could have been overwritten already.
+    /// An attached receiver holds the senders back so this is 0 unless it's been
+    /// [detached](Self::detach) or [evicted](BroadcastSender::evict_slowest). Senders and other
+    /// receivers keep moving so it's a snapshot, but it never goes down while this receiver
+    /// isn't reading
+    pub fn lag(&self) -> isize {
+        let published = self.core.sender_tracker().current();
+        // The tail isn't kept up to date while there aren't any receivers to hold it
+        let oldest = self
+            .core
+            .reader_tracker()
+            .current()
+            .max(published + 1 - self.capacity);
+        (oldest - (self.internal_cursor + 1)).max(0)
+    }
+    /// How far into the stream this receiver has read. This is the number of messages sent to
+    /// the channel up to and including the last one this receiver read. A receiver that was
+    /// created after messages had been sent, or one that skipped messages while it was
+    /// detached, will have received fewer than this.
+    /// The difference with [BroadcastSender::total_sent] is how far behind the receiver is
+    pub fn total_received(&self) -> u64 {
+        (self.internal_cursor + 1) as u64
+    }
+    /// The receiver's view of the channel as `(internal_cursor, committed_cache, capacity)`.
+    /// `internal_cursor` is the id of the last message read and `committed_cache` the last
+    /// published id the receiver has seen. Both start at -1. For tests only
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn debug_internals(&self) -> (isize, isize, isize) {
+        (self.internal_cursor, self.committed_cache, self.capacity)
+    }
+    /// Identifies this receiver. Every receiver created on a channel has a different id
+    pub fn id(&self) -> usize {
+        self.state.id()
+    }
+    /// Is this receiver currently detached from the channel
+    pub fn is_detached(&self) -> bool {
+        self.shared_cursor.is_none()
+    }
+    /// Has every sender been dropped, or the channel been [shut down](BroadcastSender::shutdown),
+    /// with nothing left for this receiver to read. Never blocks.
+    /// A receiver-only channel is disconnected until a sender is created from one of its
+    /// receivers
+    pub fn is_disconnected(&self) -> bool {
+        // Senders publish before they're dropped so once there are none everything's visible
+        (self.core.senders() == 0 || self.core.is_closed())
+            && self.internal_cursor >= self.core.sender_tracker().current()
+    }
+    /// How long it's been since anything was published to the channel, or since the channel was
+    /// created if nothing has been. A sender that's stuck rather than dropped doesn't
+    /// [disconnect](Self::is_disconnected) the channel but shows up here
+    #[cfg(feature = "heartbeat")]
+    pub fn last_publish_elapsed(&self) -> Duration {
+        self.core.last_publish_elapsed()
+    }
+    /// Walk every message that's still in the channel, oldest first, without reading any of them
+    /// or touching any cursors. Meant for inspecting what's in a stuck pipeline from another
+    /// thread.
+    ///
+    /// # Safety
+    /// Nothing can be sent on the channel while the iterator, or anything it's given out, is
+    /// alive. This receiver doesn't hold back the senders for messages it's already read and
+    /// they could be overwritten, and dropped, while they're being looked at
+    pub unsafe fn audit(&self) -> impl Iterator<Item = &T> + '_ {
+        let head = self.core.sender_tracker().current() + 1;
+        let oldest = (head - self.capacity).max(0);
+        // The caller guarantees nothing is being written
+        (oldest..head).map(move |id| unsafe { self.core.slot(id) })
+    }
+    /// Hand a value this receiver has finished with back to the senders. The next
+    /// [send_with](BroadcastSender::send_with) gets it to fill and send again instead of
+    /// allocating a new one. The channel keeps as many as it has slots and drops the rest.
+    ///
+    /// The value doesn't have to have come from this channel. Anything that's handed back is
+    /// owned by the channel until a sender takes it, or the channel is dropped
+    pub fn return_buffer(&mut self, value: T) {
+        self.core.return_buffer(value)
+    }
+    /// Package the receiver up to be moved to another thread. It keeps its place in the
+    /// channel, and keeps holding back the senders, until it's [claimed](ReceiverToken::claim)
+    /// on the other side. Unlike cloning it there's only ever one position in the reader tracker
+    pub fn transfer(self) -> ReceiverToken<T> {
+        ReceiverToken { receiver: self }
+    }
+    /// Is there anything to read. A detached receiver might have something once it's reattached
+    pub(crate) fn has_pending(&self) -> bool {
+        self.is_detached() || self.core.sender_tracker().current() > self.internal_cursor
+    }
+    /// Start listening for the next message to be published. The listener can be awaited
+    pub(crate) fn listen(&self) -> event_listener::EventListener {
+        self.core.sender_tracker().wait_strategy().listen()
+    }
+    /// A view of the channel's state for monitoring
+    pub fn stats(&self) -> ChannelStats<T> {
+        self.core.clone().into()
+    }
+    /// Something that can create receivers on this channel later without holding up the senders
+    /// in the meantime
+    pub fn receiver_factory(&self) -> ReceiverFactory<T> {
+        self.core.clone().into()
+    }
+    /// Rejoin a channel that's been recycled from the start. The receiver must be detached
+    pub(crate) fn recycle(&mut self) {
+        debug_assert!(self.is_detached());
+        self.internal_cursor = -1;
+        self.committed_cache = -1;
+        // Credit from before counts ids that are going to be sent again
+        self.state = self.core.receivers().new_detached();
+        self.reattach()
+            .expect("couldn't reattach the receiver to the channel");
+    }
     pub(crate) fn get_core(&self) -> Arc<Ring<T>> {
         self.core.clone()
     }
+    /// Move on to the next value, waiting for it if needed, and pass a reference to it to `f`.
+    /// The slot is held in the reader tracker until the next read so it can't be overwritten
+    /// while `f` is running
+    /// Fails with [ReceiverError::Evicted] if a sender has evicted the receiver. It's detached
+    /// after that and the next call reattaches it.
+    /// Fails with [ReceiverError::NoNewData] if `wait` is false and there's nothing to read
+    #[inline(always)]
+    fn recv_with<U>(&mut self, wait: bool, f: impl FnOnce(&T) -> U) -> Result<U, ReceiverError> {
+        let value = self.recv_held(wait, f)?;
+        self.end();
+        Ok(value)
+    }
+    /// The same as [recv_with](Self::recv_with) but the receiver is left busy when it succeeds
+    /// so it can't be evicted until its next read
+    #[inline(always)]
+    fn recv_held<U>(&mut self, wait: bool, f: impl FnOnce(&T) -> U) -> Result<U, ReceiverError> {
+        self.release_borrow();
+        if self.is_detached() {
+            // Registration is retried until it succeeds so this can't fail
+            self.reattach()
+                .expect("couldn't reattach the receiver to the channel");
+        }
+        let next = self.internal_cursor + 1;
+        if self.committed_cache < next {
+            self.committed_cache = if wait {
+                self.core.sender_tracker().wait_for(next)
+            } else {
+                self.core.sender_tracker().current()
+            };
+            if self.committed_cache < next {
+                return Err(ReceiverError::NoNewData);
+            }
+        }
+        if !self.begin() {
+            // We've already been removed from the reader tracker
+            self.shared_cursor = None;
+            return Err(ReceiverError::Evicted);
+        }
+        self.validate_read(next, next);
+        // the value has been committed so it's safe to read it! The slot can't be overwritten
+        // while we're holding the one before it. Only move on once `f` is done so that if it
+        // panics the value is read again next time
+        fence(Ordering::Acquire);
+        let unwinding = EndOnUnwind::new(self);
+        let value = unsafe { f(self.core.slot(next)) };
+        core::mem::forget(unwinding);
+        self.increment_internal();
+        self.publish_position();
+        debug_assert!(self.committed_cache >= self.internal_cursor);
+        debug_assert!(self.deferred_acks || self.shared_cursor == Some(self.internal_cursor));
+        Ok(value)
+    }
+    /// Check that the receiver is holding back the slots from `from` to `to` and that they've
+    /// all been published
+    #[inline(always)]
+    fn validate_read(&self, from: isize, to: isize) {
+        validate_ordering!(
+            self.core.reader_tracker().current() <= from,
+            "reading {from} which is behind the tail"
+        );
+        validate_ordering!(
+            to <= self.core.sender_tracker().current(),
+            "reading {to} which hasn't been published"
+        );
+    }
+
+    /// Move on past up to `max` values, waiting for at least one if `wait` is set, and pass them
+    /// to `f` as at most two slices. The receiver's position is only published once `f` is done
+    /// with them. Returns how many values were passed to `f`
+    #[inline(always)]
+    fn recv_batch_with(
+        &mut self,
+        wait: bool,
+        max: usize,
+        mut f: impl FnMut(&[T]),
+    ) -> Result<usize, ReceiverError> {
+        self.release_borrow();
+        if max == 0 {
+            return Ok(0);
+        }
+        if self.is_detached() {
+            // Registration is retried until it succeeds so this can't fail
+            self.reattach()
+                .expect("couldn't reattach the receiver to the channel");
+        }
+        let next = self.internal_cursor + 1;
+        if wait && self.committed_cache < next {
+            self.core.sender_tracker().wait_for(next);
+        }
+        // Pick up everything that's been published since
+        self.committed_cache = self.core.sender_tracker().current();
+        if self.committed_cache < next {
+            return Err(ReceiverError::NoNewData);
+        }
+        if !self.begin() {
+            // We've already been removed from the reader tracker
+            self.shared_cursor = None;
+            return Err(ReceiverError::Evicted);
+        }
+        let last = self
+            .committed_cache
+            .min(next.saturating_add(max.min(isize::MAX as usize) as isize - 1));
+        let count = (last - next + 1) as usize;
+        self.validate_read(next, last);
+        // the values have been committed so it's safe to read them!
+        fence(Ordering::Acquire);
+        let unwinding = EndOnUnwind::new(self);
+        unsafe {
+            let (first, second) = self.core.slices(next, count);
+            f(first);
+            if !second.is_empty() {
+                f(second);
+            }
+        }
+        core::mem::forget(unwinding);
+        self.internal_cursor = last;
+        self.publish_position();
+        self.end();
+        Ok(count)
+    }
+    /// Read the next value from the channel passing a reference to it to `f` rather than cloning
+    /// it. Use this when only part of the value is needed. This function will block and wait for
+    /// data to become available.
+    pub fn recv_map<U, F: FnMut(&T) -> U>(&mut self, mut f: F) -> Result<U, ReceiverError> {
+        self.recv_with(true, |value| f(value))
+    }
+
+    /// Read the next value without copying it out of the channel, waiting for it if needed. The
+    /// receiver holds on to the value's slot until it's used again, which the borrow checker
+    /// enforces, so the value can't be overwritten while it's borrowed.
+    ///
+    /// On an [evictable](crate::ChannelBuilder::evictable) channel the receiver can't be evicted
+    /// while the borrow is alive. A sender calling
+    /// [evict_slowest](BroadcastSender::evict_slowest) waits for the receiver to be used again.
+    /// Fails with [ReceiverError::Evicted] the first time it's called after the receiver was
+    /// evicted
+    pub fn recv_borrowed(&mut self) -> Result<&T, ReceiverError> {
+        let value: *const T = self.recv_held(true, |value| value as *const T)?;
+        // The receiver is holding the slot and stays busy until it's next used
+        self.borrowed = self.core.evictable();
+        Ok(unsafe { &*value })
+    }
+
+    /// Pass everything that's waiting for this receiver to `f` without copying it out of the
+    /// channel. `f` is called once, or twice when the run wraps around the end of the ring, and
+    /// nothing it's given can be overwritten until it returns. Returns how many values were
+    /// passed to `f`.
+    ///
+    /// This doesn't wait so it returns 0 if there's nothing to read. It also returns 0 the first
+    /// time it's called after the receiver was evicted. The next call reattaches it
+    pub fn consume_batch<F: FnMut(&[T])>(&mut self, f: F) -> usize {
+        self.recv_batch_with(false, usize::MAX, f).unwrap_or(0)
+    }
 }
 
 impl<T> BroadcastReceiver<T>
@@ -105,24 +746,514 @@ where
     T: Clone,
 {
     /// Read the next value from the channel. This function will block and wait for data to
-    /// become available.
+    /// become available. A [detached](Self::detach) receiver is reattached first.
+    /// So is one that's been [evicted](BroadcastSender::evict_slowest). Use [Receiver::recv] to
+    /// find out when that happens.
     pub fn recv(&mut self) -> T {
-        self.increment_internal();
-        if self.committed_cache < self.internal_cursor {
-            self.committed_cache = self.core.sender_tracker().wait_for(self.internal_cursor);
+        loop {
+            if let Ok(value) = self.recv_with(true, T::clone) {
+                return value;
+            }
+        }
+    }
+
+    /// Iterate over the values sent to this receiver, waiting for each one with
+    /// [recv](Self::recv). The channel never runs out so this doesn't end on its own
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(move || Some(self.recv()))
+    }
+
+    /// Iterate over the values that have already been sent to this receiver without waiting.
+    /// Ends as soon as [try_recv](Self::try_recv) fails, so when there's nothing new to read or
+    /// the receiver has been evicted
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(move || self.try_recv().ok())
+    }
+
+    /// Read the latest value in the channel skipping everything before it, or wait for the next
+    /// one if there's nothing new. Useful for a consumer that only cares about the current
+    /// state but shouldn't spin while nothing changes. Anything sent while it's deciding is
+    /// read rather than waited for. Fails like [Receiver::recv]
+    pub fn recv_latest_or_wait(&mut self) -> Result<T, ReceiverError> {
+        loop {
+            let latest = self.core.sender_tracker().current();
+            // A detached receiver is reattached at the latest anyway
+            if !self.is_detached() && latest > self.internal_cursor + 1 {
+                self.fast_forward(Sequence(latest as u64))?;
+            }
+            match self.try_recv() {
+                Err(ReceiverError::NoNewData) => {}
+                result => return result,
+            }
+            let listener = self.listen();
+            if self.has_pending() || self.is_disconnected() {
+                continue;
+            }
+            listener.wait();
         }
-        if self.internal_cursor > 0 {
-            self.publish_position();
+    }
+
+    /// Read everything that's been sent to this receiver into `out`, waiting for at least one
+    /// value to be available. The values are copied out in at most two slices, two when they
+    /// wrap around the end of the ring, and the receiver's position is only moved once they've
+    /// been copied. Returns how many values were read
+    pub fn batch_recv(&mut self, out: &mut Vec<T>) -> usize {
+        self.batch_recv_max(out, usize::MAX)
+    }
+
+    /// Read up to `max` values into `out`, waiting for at least one to be available. Everything
+    /// else that's already been sent is read in at most two slice copies and the receiver's
+    /// position is only moved once. Returns how many values were read.
+    /// Keeping `max` small bounds how much `out` grows when the receiver is a long way behind
+    pub fn batch_recv_max(&mut self, out: &mut Vec<T>, max: usize) -> usize {
+        loop {
+            // An evicted receiver is detached so the next attempt reattaches it
+            if let Ok(count) = self.recv_batch_with(true, max, |values| {
+                out.extend_from_slice(values);
+            }) {
+                return count;
+            }
         }
-        debug_assert!(self.committed_cache >= self.internal_cursor);
-        let index = self.internal_cursor.pow_2_mod(self.capacity) as usize;
-        // the value has been committed so it's safe to read it!
-        fence(Ordering::Acquire);
-        let value;
-        unsafe {
-            value = (*self.core.ring()).get_unchecked(index).clone();
+    }
+
+    /// [batch_recv_max](Self::batch_recv_max) that reads everything that's waiting and says when
+    /// messages were lost. The range is checked before anything is copied and on an
+    /// [evictable](crate::ChannelBuilder::evictable) channel a receiver that's been evicted,
+    /// and so might have had part of it overwritten, copies nothing. It's reattached at the
+    /// latest message and the error says how many were skipped. The next call reads from there
+    pub fn batch_recv_checked(&mut self, out: &mut Vec<T>) -> Result<usize, OverrunError> {
+        loop {
+            match self.recv_batch_with(true, usize::MAX, |values| out.extend_from_slice(values)) {
+                Ok(count) => return Ok(count),
+                Err(ReceiverError::Evicted) => {
+                    // Registration is retried until it succeeds so this can't fail
+                    let lost = self
+                        .reattach()
+                        .expect("couldn't reattach the receiver to the channel");
+                    return Err(OverrunError { lost });
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Read everything that's waiting for this receiver into `out` without waiting. Returns how
+    /// many values were read which is 0 if there weren't any. It's also 0 the first time it's
+    /// called after the receiver was evicted. The next call reattaches it
+    pub fn try_recv_batch(&mut self, out: &mut Vec<T>) -> usize {
+        self.recv_batch_with(false, usize::MAX, |values| out.extend_from_slice(values))
+            .unwrap_or(0)
+    }
+
+    /// Read the next value from the channel if there is one without waiting. Fails with
+    /// [ReceiverError::NoNewData] if there isn't or [ReceiverError::Evicted] the first time it's
+    /// called after the receiver was evicted. Once the receiver is
+    /// [disconnected](Self::is_disconnected) it fails with [ReceiverError::Disconnected]
+    pub fn try_recv(&mut self) -> Result<T, ReceiverError> {
+        match self.recv_with(false, T::clone) {
+            Err(ReceiverError::NoNewData) if self.is_disconnected() => {
+                Err(ReceiverError::Disconnected)
+            }
+            result => result,
+        }
+    }
+
+    /// Wait for the next value like [Receiver::recv] without blocking the thread. The task is
+    /// woken when something is published
+    pub async fn async_recv(&mut self) -> Result<T, ReceiverError> {
+        loop {
+            match self.try_recv() {
+                Err(ReceiverError::NoNewData) => {}
+                result => return result,
+            }
+            let listener = self.listen();
+            // Something could have been published, or the last sender gone, before we started
+            // listening
+            if self.has_pending() || self.is_disconnected() {
+                continue;
+            }
+            listener.await;
+        }
+    }
+
+    /// Wait for the next value like [Receiver::recv] but give up with [ReceiverError::Timeout]
+    /// once `timeout` has passed. Nothing is read when it times out so the next call carries on
+    /// from the same place
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, ReceiverError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.try_recv() {
+                Err(ReceiverError::NoNewData) => {}
+                result => return result,
+            }
+            let listener = self.listen();
+            // Something could have been published, or the last sender gone, before we started
+            // listening
+            if self.has_pending() || self.is_disconnected() {
+                continue;
+            }
+            if !listener.wait_deadline(deadline) && !self.has_pending() {
+                return Err(ReceiverError::Timeout);
+            }
+        }
+    }
+
+    /// Wait for the next value calling `beat` every `interval` while there's nothing to read.
+    /// Useful for keeping a watchdog happy through quiet periods. `beat` is never called once a
+    /// value has been read.
+    /// Fails with [ReceiverError::Evicted] the first time it's called after the receiver was
+    /// evicted
+    pub fn recv_with_heartbeat(
+        &mut self,
+        interval: Duration,
+        mut beat: impl FnMut(),
+    ) -> Result<T, ReceiverError> {
+        let mut next_beat = Instant::now() + interval;
+        loop {
+            match self.try_recv() {
+                Err(ReceiverError::NoNewData) => {}
+                result => return result,
+            }
+            let listener = self.listen();
+            // Something could have been published before we started listening
+            if self.has_pending() {
+                continue;
+            }
+            if !listener.wait_deadline(next_beat) {
+                beat();
+                // Don't try to catch up if the beat took a while
+                next_beat = Instant::now() + interval;
+            }
+        }
+    }
+
+    /// Read the next value waiting for it if needed along with what a consumer needs to know to
+    /// adapt to how it's keeping up. See [RecvInfo]. Fails with [ReceiverError::Evicted] the
+    /// first time it's called after the receiver was evicted
+    pub fn recv_detailed(&mut self) -> Result<RecvInfo<T>, ReceiverError> {
+        let blocked = self.committed_cache <= self.internal_cursor && !self.has_pending();
+        let value = self.recv_with(true, T::clone)?;
+        let pending = self.core.sender_tracker().current() - self.internal_cursor;
+        Ok(RecvInfo {
+            value,
+            sequence: Sequence::from_cursor(self.internal_cursor),
+            pending: pending.max(0) as usize,
+            blocked,
+        })
+    }
+
+    /// The same as [recv](Self::recv) but also returns where the message is in the stream
+    pub fn recv_with_seq(&mut self) -> (Sequence, T) {
+        let value = self.recv();
+        (Sequence::from_cursor(self.internal_cursor), value)
+    }
+
+    /// Copy every message that's waiting for this receiver, oldest first, without reading any
+    /// of them. This is a diagnostic for looking at what a stuck receiver is sitting on. It isn't
+    /// a way to consume messages and the receiver's position doesn't change.
+    ///
+    /// Only the slots a receiver is holding back are safe to look at. Anything behind it can be
+    /// overwritten at any time so a [detached](Self::detach) receiver always gets nothing.
+    pub fn snapshot(&self) -> Vec<T> {
+        self.hold(|shared_cursor| {
+            // The receiver can be one behind its shared position after a reattach
+            let from = (self.internal_cursor + 1).max(shared_cursor);
+            let to = self.core.sender_tracker().current();
+            fence(Ordering::Acquire);
+            (from..=to)
+                .map(|id| unsafe { self.core.read(id) })
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Copy up to `n` of the messages waiting for this receiver into `out` without reading them.
+    /// Returns how many were copied. The receiver's position doesn't change so they're still
+    /// there to be read.
+    ///
+    /// The receiver holds back the senders so nothing it hasn't read can be overwritten while
+    /// it's attached. A [detached](Self::detach) receiver never peeks anything as it isn't
+    /// holding anything back
+    pub fn peek_ahead(&self, n: usize, out: &mut Vec<T>) -> usize {
+        self.hold(|shared_cursor| {
+            // The receiver can be one behind its shared position after a reattach
+            let from = (self.internal_cursor + 1).max(shared_cursor);
+            let available = self.core.sender_tracker().current() + 1 - from;
+            let count = available.clamp(0, isize::MAX).min(n as isize) as usize;
+            fence(Ordering::Acquire);
+            let (first, second) = unsafe { self.core.slices(from, count) };
+            out.extend_from_slice(first);
+            out.extend_from_slice(second);
+            count
+        })
+        .unwrap_or(0)
+    }
+
+    /// Copy the latest message that's been published without reading it. The receiver's
+    /// position doesn't change so it holds back the senders exactly as before.
+    ///
+    /// This is best effort. Only the slots the receiver is holding back can't be overwritten
+    /// while they're copied, so it's None if the latest message is behind them. That's the
+    /// case for a [detached](Self::detach) receiver and for one that was just reattached and
+    /// hasn't had anything new since. It's also None until something has been published
+    pub fn peek_latest(&self) -> Option<T> {
+        self.hold(|shared_cursor| {
+            let latest = self.core.sender_tracker().current();
+            if latest < shared_cursor {
+                return None;
+            }
+            fence(Ordering::Acquire);
+            Some(unsafe { self.core.read(latest) })
+        })
+        .flatten()
+    }
+
+    /// [recv_borrowed](Self::recv_borrowed) as a [Cow] so that the value is only cloned if
+    /// it's needed after all with [into_owned](Cow::into_owned). The receiver holds on to the
+    /// value's slot until the `Cow` is gone and the receiver is used again
+    pub fn recv_cow(&mut self) -> Result<Cow<'_, T>, ReceiverError> {
+        self.recv_borrowed().map(Cow::Borrowed)
+    }
+
+    /// Turn the receiver into one that passes every value it reads through `f`. Useful for
+    /// building the stages of a pipeline
+    ///
+    /// ```
+    /// let (mut sender, receiver) = nexusq::channel(4).expect("couldn't create channel").dissolve();
+    /// let mut lengths = receiver.map_into(|value: &str| value.len());
+    /// sender.send("four");
+    /// assert_eq!(lengths.recv(), 4);
+    /// ```
+    pub fn map_into<U, F: FnMut(T) -> U>(self, f: F) -> MappedReceiver<T, U, F> {
+        MappedReceiver {
+            receiver: self,
+            f,
+            _mapped: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> BroadcastReceiver<T>
+where
+    T: Copy,
+{
+    /// Receiving a [Copy] type is a plain copy out of the channel as that's all its
+    /// [clone](Clone::clone) can be. It only exists for [Copy] types so naming it checks, at
+    /// compile time, that a message type takes the fast path
+    ///
+    /// ```
+    /// const _: () = assert!(nexusq::BroadcastReceiver::<u64>::IS_TRIVIAL_COPY);
+    /// ```
+    ///
+    /// ```compile_fail,E0599
+    /// const _: () = assert!(nexusq::BroadcastReceiver::<String>::IS_TRIVIAL_COPY);
+    /// ```
+    pub const IS_TRIVIAL_COPY: bool = true;
+}
+
+impl<T> BroadcastReceiver<T>
+where
+    T: Clone + PartialEq,
+{
+    /// Read the next value that's different to the last one read, skipping over any repeats.
+    /// Useful when senders keep re-sending the current state and only changes matter.
+    /// Repeats are read like any other value so they don't hold up the senders. This only blocks
+    /// once the receiver has caught up without seeing a change.
+    ///
+    /// A receiver that has just been created or reattached always gets the next value.
+    /// Fails with [ReceiverError::Evicted] the first time it's called after the receiver was
+    /// evicted
+    pub fn recv_changed(&mut self) -> Result<T, ReceiverError> {
+        let core = self.core.clone();
+        loop {
+            let previous = self.internal_cursor;
+            // The last value read can only be looked at while we're still holding its slot
+            let holding_previous =
+                previous >= 0 && matches!(self.shared_cursor, Some(at) if at <= previous);
+            let mut changed = None;
+            self.recv_batch_with(true, 1, |values| {
+                let value = &values[0];
+                if !holding_previous || unsafe { core.slot(previous) } != value {
+                    changed = Some(value.clone());
+                }
+            })?;
+            if let Some(value) = changed {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// A receiver that passes every value it reads through a function. See
+/// [map_into](BroadcastReceiver::map_into)
+#[derive(Debug)]
+pub struct MappedReceiver<T, U, F> {
+    receiver: BroadcastReceiver<T>,
+    f: F,
+    _mapped: core::marker::PhantomData<fn() -> U>,
+}
+
+impl<T, U, F> MappedReceiver<T, U, F>
+where
+    T: Clone,
+    F: FnMut(T) -> U,
+{
+    /// Read the next value and map it, waiting for one if needed. The same as
+    /// [BroadcastReceiver::recv]
+    pub fn recv(&mut self) -> U {
+        (self.f)(self.receiver.recv())
+    }
+
+    /// Read the next value and map it if there is one without waiting. The same as
+    /// [BroadcastReceiver::try_recv]
+    pub fn try_recv(&mut self) -> Result<U, ReceiverError> {
+        self.receiver.try_recv().map(&mut self.f)
+    }
+
+    /// The receiver underneath
+    pub fn get_ref(&self) -> &BroadcastReceiver<T> {
+        &self.receiver
+    }
+
+    /// Get the receiver back without the function
+    pub fn into_inner(self) -> BroadcastReceiver<T> {
+        self.receiver
+    }
+}
+
+impl<T, U, F> Clone for MappedReceiver<T, U, F>
+where
+    F: Clone,
+{
+    /// Clones the receiver, at the same point in the stream, and the function
+    fn clone(&self) -> Self {
+        Self {
+            receiver: self.receiver.clone(),
+            f: self.f.clone(),
+            _mapped: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, U, F> Receiver<U> for MappedReceiver<T, U, F>
+where
+    T: Clone,
+    F: FnMut(T) -> U + Clone,
+{
+    /// The same as [Receiver::recv] on [BroadcastReceiver] with the value mapped
+    fn recv(&mut self) -> Result<U, ReceiverError> {
+        Receiver::recv(&mut self.receiver).map(&mut self.f)
+    }
+}
+
+/// Somewhere to read batches into that's only allocated once. Each read replaces the previous
+/// batch
+///
+/// ```
+/// let (mut sender, mut receiver) = nexusq::channel(8).expect("couldn't create channel").dissolve();
+/// let mut batch = nexusq::BatchBuffer::new(4);
+/// for i in 0..6 {
+///     sender.send(i);
+/// }
+/// assert_eq!(batch.recv_into(&mut receiver), &[0, 1, 2, 3]);
+/// assert_eq!(batch.recv_into(&mut receiver), &[4, 5]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchBuffer<T> {
+    values: Vec<T>,
+    max: usize,
+}
+
+impl<T> BatchBuffer<T>
+where
+    T: Clone,
+{
+    /// Batches are at most `max` long which is all that's ever allocated
+    pub fn new(max: usize) -> Self {
+        Self {
+            values: Vec::with_capacity(max),
+            max,
+        }
+    }
+
+    /// Read the next batch waiting for at least one value. See
+    /// [batch_recv_max](BroadcastReceiver::batch_recv_max)
+    pub fn recv_into(&mut self, receiver: &mut BroadcastReceiver<T>) -> &[T] {
+        self.values.clear();
+        receiver.batch_recv_max(&mut self.values, self.max);
+        &self.values
+    }
+
+    /// Read the next batch without waiting. The batch is empty if there's nothing to read or the
+    /// receiver has just been evicted
+    pub fn try_recv_into(&mut self, receiver: &mut BroadcastReceiver<T>) -> &[T] {
+        self.values.clear();
+        let _ = receiver.recv_batch_with(false, self.max, |values| {
+            self.values.extend_from_slice(values);
+        });
+        &self.values
+    }
+}
+
+/// A value read with [recv_detailed](BroadcastReceiver::recv_detailed)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecvInfo<T> {
+    pub value: T,
+    /// Where the value is in the stream
+    pub sequence: Sequence,
+    /// How many more values were waiting to be read right after this one was
+    pub pending: usize,
+    /// Whether there was nothing to read so the receiver had to wait for the value
+    pub blocked: bool,
+}
+
+/// A [BroadcastReceiver] on its way to another thread. See [transfer](BroadcastReceiver::transfer)
+#[derive(Debug)]
+pub struct ReceiverToken<T> {
+    receiver: BroadcastReceiver<T>,
+}
+
+impl<T> ReceiverToken<T> {
+    /// Get the receiver back exactly where it left off
+    pub fn claim(self) -> BroadcastReceiver<T> {
+        self.receiver
+    }
+}
+
+/// Creates receivers on a channel when they're needed. It doesn't hold a position in the channel
+/// itself so unlike keeping a receiver around to clone from it never holds up the senders.
+/// Get one from [BroadcastSender::receiver_factory] or [BroadcastReceiver::receiver_factory]
+#[derive(Debug)]
+pub struct ReceiverFactory<T> {
+    core: Arc<Ring<T>>,
+}
+
+impl<T> Clone for ReceiverFactory<T> {
+    fn clone(&self) -> Self {
+        Self {
+            core: self.core.clone(),
         }
-        value
+    }
+}
+
+impl<T> From<Arc<Ring<T>>> for ReceiverFactory<T> {
+    fn from(core: Arc<Ring<T>>) -> Self {
+        Self { core }
+    }
+}
+
+impl<T> ReceiverFactory<T> {
+    /// Creates a new receiver at the most recent entry in the stream
+    pub fn receiver(&self) -> Result<BroadcastReceiver<T>, ReceiverError> {
+        self.core.clone().try_into()
+    }
+
+    /// [receiver](Self::receiver) choosing what happens if nothing has been sent yet
+    pub fn receiver_with(
+        &self,
+        policy: RegisterPolicy,
+    ) -> Result<BroadcastReceiver<T>, ReceiverError> {
+        BroadcastReceiver::join(self.core.clone(), policy)
     }
 }
 
@@ -130,14 +1261,161 @@ impl<T> Receiver<T> for BroadcastReceiver<T>
 where
     T: Clone,
 {
+    /// Fails with [ReceiverError::Evicted] the first time it's called after the receiver was
+    /// evicted. Fails with [ReceiverError::Disconnected] instead of waiting once the receiver is
+    /// [disconnected](BroadcastReceiver::is_disconnected)
     fn recv(&mut self) -> Result<T, ReceiverError> {
-        Ok(BroadcastReceiver::recv(self))
+        loop {
+            match self.try_recv() {
+                Err(ReceiverError::NoNewData) => {}
+                result => return result,
+            }
+            let listener = self.listen();
+            // Something might have been sent, or the last sender gone, before we started listening
+            if self.has_pending() || self.is_disconnected() {
+                continue;
+            }
+            listener.wait();
+        }
     }
 }
 
 #[cfg(test)]
 mod receiver_tests {
     use crate::channel::*;
+    use crate::{BatchBuffer, OverrunError, Receiver, RecvInfo, RegisterPolicy};
+
+    #[test]
+    fn batch_recv_drops() {
+        use std::sync::Arc;
+
+        let value = Arc::new(());
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut out = Vec::new();
+        for i in 0..7 {
+            sender.send(value.clone());
+            assert_eq!(Arc::strong_count(&value), i + 2);
+        }
+        assert_eq!(receiver.batch_recv_max(&mut out, 7), 7);
+        assert_eq!(Arc::strong_count(&value), 15);
+        out.clear();
+        assert_eq!(Arc::strong_count(&value), 8);
+        // Overwrite what was read. Every lap after the first drops exactly what it replaces and
+        // the second batch wraps around the end of the ring
+        for lap in 0..3 {
+            for _ in 0..7 {
+                sender.send(value.clone());
+            }
+            assert_eq!(receiver.batch_recv_max(&mut out, 7), 7);
+            assert_eq!(Arc::strong_count(&value), 16, "lap {lap}");
+            out.clear();
+            assert_eq!(Arc::strong_count(&value), 9, "lap {lap}");
+        }
+        drop(sender);
+        drop(receiver);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn register_policy() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let factory = receiver.receiver_factory();
+        assert!(matches!(
+            receiver.add_stream_with(RegisterPolicy::ErrorIfEmpty),
+            Err(ReceiverError::NoNewData)
+        ));
+        assert!(matches!(
+            factory.receiver_with(RegisterPolicy::ErrorIfEmpty),
+            Err(ReceiverError::NoNewData)
+        ));
+        let mut waiting = receiver
+            .add_stream_with(RegisterPolicy::BlockForFuture)
+            .expect("couldn't create receiver");
+        sender.send(1);
+        let mut probe = factory
+            .receiver_with(RegisterPolicy::ErrorIfEmpty)
+            .expect("couldn't create receiver");
+        assert_eq!(waiting.recv(), 1);
+        assert_eq!(probe.recv(), 1);
+    }
+
+    #[test]
+    fn map_into() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut mapped = receiver.map_into(|value: usize| value * 2);
+        assert!(matches!(mapped.try_recv(), Err(ReceiverError::NoNewData)));
+        sender.send(1);
+        sender.send(2);
+        let mut other = mapped.clone();
+        assert_eq!(mapped.recv(), 2);
+        assert_eq!(Receiver::recv(&mut mapped).expect("couldn't receive"), 4);
+        assert_eq!(other.try_recv().expect("couldn't receive"), 2);
+        drop(sender);
+        assert!(matches!(
+            Receiver::recv(&mut mapped),
+            Err(ReceiverError::Disconnected)
+        ));
+        assert_eq!(other.into_inner().recv(), 2);
+    }
+
+    #[test]
+    fn receiver_factory() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let factory = receiver.receiver_factory();
+        drop(receiver);
+        // Nothing is holding up the sender
+        for i in 0..10 {
+            sender.send(i);
+        }
+        let factory = std::thread::spawn(move || factory.clone())
+            .join()
+            .expect("thread panicked");
+        let mut receiver = factory.receiver().expect("couldn't create receiver");
+        sender.send(10);
+        assert_eq!(receiver.recv(), 9);
+        assert_eq!(receiver.recv(), 10);
+        assert_eq!(receiver.stats().receiver_positions().len(), 1);
+    }
+
+    #[test]
+    fn clone_panics() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct Fragile(usize, Arc<AtomicUsize>);
+
+        impl Clone for Fragile {
+            fn clone(&self) -> Self {
+                if self.1.fetch_add(1, Ordering::Relaxed) == 2 {
+                    panic!("third clone");
+                }
+                Self(self.0, self.1.clone())
+            }
+        }
+
+        let (mut sender, mut receiver) = crate::ChannelBuilder::new(8)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let clones = Arc::new(AtomicUsize::new(0));
+        for i in 0..5 {
+            sender.send(Fragile(i, clones.clone()));
+        }
+        let mut read = Vec::new();
+        while read.len() < 5 {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| receiver.recv()));
+            if let Ok(value) = result {
+                read.push(value.0);
+            }
+        }
+        assert_eq!(read, vec![0, 1, 2, 3, 4]);
+        assert_eq!(clones.load(Ordering::Relaxed), 6);
+        assert_eq!(receiver.total_received(), 5);
+        // The panic didn't leave the receiver busy
+        assert_eq!(sender.evict_slowest(), Some(receiver.id()));
+    }
 
     #[test]
     fn receiver_from_sender() {
@@ -149,4 +1427,1121 @@ mod receiver_tests {
         let v = receiver.recv();
         assert_eq!(v, 42);
     }
+
+    #[test]
+    fn recv_before_first_send() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        // Nothing has been written so the receiver sits just before the first slot
+        assert_eq!(receiver.total_received(), 0);
+        assert_eq!(receiver.debug_internals(), (-1, -1, 4));
+        assert!(matches!(receiver.try_recv(), Err(ReceiverError::NoNewData)));
+        let (started, waiting) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            started.send(()).expect("test has finished");
+            let first = receiver.recv_with_seq();
+            (first, receiver.total_received())
+        });
+        waiting.recv().expect("reader panicked");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        // Reading slot 0 before it's written would read uninitialised memory
+        assert!(!reader.is_finished());
+        sender.send(String::from("first"));
+        let (first, received) = reader.join().expect("reader panicked");
+        assert_eq!(first, (Sequence(0), String::from("first")));
+        assert_eq!(received, 1);
+    }
+
+    #[test]
+    fn debug_internals() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert_eq!(sender.debug_internals(), (0, 4));
+        for i in 0..5 {
+            sender.send(i);
+            assert_eq!(receiver.recv(), i);
+        }
+        // Sending the fifth message made the sender look at where the receiver was
+        assert_eq!(sender.debug_internals(), (3, 4));
+        sender.send(5);
+        sender.send(6);
+        assert_eq!(receiver.recv(), 5);
+        assert_eq!(receiver.debug_internals(), (5, 6, 4));
+    }
+
+    #[test]
+    fn detach_reattach() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(1);
+        assert_eq!(receiver.recv(), 1);
+        receiver.detach();
+        assert!(receiver.is_detached());
+        // Would block forever if the detached receiver was still holding the tail
+        for i in 2..20 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.reattach().expect("couldn't reattach"), 17);
+        assert!(!receiver.is_detached());
+        assert_eq!(receiver.recv(), 19);
+        sender.send(20);
+        assert_eq!(receiver.recv(), 20);
+        assert_eq!(receiver.reattach().expect("couldn't reattach"), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "heartbeat")]
+    fn last_publish_elapsed() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel").dissolve();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(receiver.last_publish_elapsed() >= std::time::Duration::from_millis(20));
+        sender.send(1);
+        assert!(receiver.last_publish_elapsed() < std::time::Duration::from_millis(20));
+    }
+
+    #[test]
+    fn reattach_nothing_missed() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(1);
+        assert_eq!(receiver.recv(), 1);
+        receiver.detach();
+        assert_eq!(receiver.reattach().expect("couldn't reattach"), 0);
+        sender.send(2);
+        assert_eq!(receiver.recv(), 2);
+    }
+
+    #[test]
+    fn recv_reattaches() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        receiver.detach();
+        for i in 0..10 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv(), 9);
+        receiver.detach();
+        for i in 10..20 {
+            sender.send(i);
+        }
+        assert_eq!(Receiver::recv(&mut receiver).expect("recv failed"), 19);
+        assert!(!receiver.is_detached());
+    }
+
+    #[test]
+    fn clone_detached() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        receiver.detach();
+        let mut cloned = receiver.clone();
+        assert!(cloned.is_detached());
+        drop(receiver);
+        // The clone mustn't be holding the tail either
+        for i in 0..10 {
+            sender.send(i);
+        }
+        assert_eq!(cloned.recv(), 9);
+    }
+
+    #[test]
+    fn detach_wakes_blocked_sender() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let writer = std::thread::spawn(move || {
+            for i in 0..10 {
+                sender.send(i);
+            }
+        });
+        // Give the writer time to fill the buffer and block on the receiver
+        std::thread::sleep(core::time::Duration::from_millis(50));
+        assert!(!writer.is_finished());
+        receiver.detach();
+        writer.join().expect("writer panicked");
+    }
+
+    #[test]
+    fn detach_reattach_while_sending() {
+        const NUM_WRITERS: usize = 2;
+        const NUM_CHURNERS: usize = 2;
+        const NUM_TO_WRITE: usize = 200;
+        const WIDTH: usize = 16;
+
+        // Every element of a message is the same so a slot that's overwritten while it's being
+        // read shows up as a message that doesn't match itself
+        fn check(message: &[usize]) -> (usize, usize) {
+            assert_eq!(message.len(), WIDTH);
+            let value = message[0];
+            assert!(message.iter().all(|v| *v == value), "torn read {message:?}");
+            (value / NUM_TO_WRITE, value % NUM_TO_WRITE)
+        }
+
+        let (mut sender, mut receiver) = channel::<Vec<usize>>(4)
+            .expect("couldn't create channel")
+            .dissolve();
+        let churners: Vec<_> = (0..NUM_CHURNERS)
+            .map(|_| {
+                let mut churner = receiver.clone();
+                std::thread::spawn(move || {
+                    let mut last_seen = [None; NUM_WRITERS];
+                    loop {
+                        for _ in 0..3 {
+                            let message = churner.recv();
+                            if message.is_empty() {
+                                return;
+                            }
+                            let (writer, seq) = check(&message);
+                            assert!(last_seen[writer] < Some(seq));
+                            last_seen[writer] = Some(seq);
+                        }
+                        churner.detach();
+                        std::thread::yield_now();
+                        churner.reattach().expect("couldn't reattach");
+                    }
+                })
+            })
+            .collect();
+        let writers: Vec<_> = (0..NUM_WRITERS)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for seq in 0..NUM_TO_WRITE {
+                        sender.send(vec![writer * NUM_TO_WRITE + seq; WIDTH]);
+                    }
+                })
+            })
+            .collect();
+
+        // This receiver never detaches so it must see everything in order
+        let mut next = [0; NUM_WRITERS];
+        for _ in 0..NUM_WRITERS * NUM_TO_WRITE {
+            let (writer, seq) = check(&receiver.recv());
+            assert_eq!(next[writer], seq);
+            next[writer] += 1;
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        drop(receiver);
+        // The churners can skip past the end of the writes so tell them explicitly when to stop.
+        // A churner that's detached will reattach at this message
+        sender.send(Vec::new());
+        for churner in churners {
+            churner.join().expect("churning reader panicked");
+        }
+    }
+
+    #[test]
+    fn recv_map() {
+        #[derive(Debug)]
+        struct NotClone {
+            id: usize,
+            _name: String,
+        }
+        let (mut sender, mut receiver) = channel::<NotClone>(4)
+            .expect("couldn't create channel")
+            .dissolve();
+        for id in 0..10 {
+            sender.send(NotClone {
+                id,
+                _name: id.to_string(),
+            });
+            let got = receiver.recv_map(|v| v.id).expect("recv_map failed");
+            assert_eq!(got, id);
+        }
+    }
+
+    #[test]
+    fn total_received() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        sender.send(1);
+        sender.send(2);
+        assert_eq!(receiver.total_received(), 0);
+        receiver.recv();
+        assert_eq!(receiver.total_received(), 1);
+        let mut late = receiver.add_stream().expect("couldn't add stream");
+        // The late receiver starts at the most recent message
+        assert_eq!(late.recv(), 2);
+        // It's only read one message but it's as far into the stream as the sender
+        assert_eq!(late.total_received(), 2);
+        assert_eq!(sender.total_sent() - receiver.total_received(), 1);
+    }
+
+    #[test]
+    fn len_and_lag() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert!(receiver.is_empty());
+        sender.send(0);
+        sender.send(1);
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.recv(), 0);
+        assert_eq!(receiver.len(), 1);
+        assert_eq!(receiver.lag(), 0);
+        receiver.detach();
+        for i in 2..9 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.len(), 8);
+        assert!(!receiver.is_empty());
+        // Only 5 to 8 are still in the channel
+        assert_eq!(receiver.lag(), 4);
+        assert_eq!(receiver.recv(), 8);
+        assert!(receiver.is_empty());
+        assert_eq!(receiver.lag(), 0);
+    }
+
+    #[test]
+    fn iter() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let writer = std::thread::spawn(move || {
+            for i in 0..10 {
+                sender.send(i);
+            }
+        });
+        assert_eq!(
+            receiver.iter().take(10).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        writer.join().expect("writer panicked");
+    }
+
+    #[test]
+    fn try_iter() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert_eq!(receiver.try_iter().next(), None);
+        for i in 0..3 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+        sender.send(3);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn batch_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut out = Vec::new();
+        for i in 0..3 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.batch_recv(&mut out), 3);
+        assert_eq!(out, vec![0, 1, 2]);
+        // Starts at index 3 and wraps around to the front
+        for i in 3..6 {
+            sender.send(i);
+        }
+        out.clear();
+        assert_eq!(receiver.batch_recv(&mut out), 3);
+        assert_eq!(out, vec![3, 4, 5]);
+        // Waits for the first one and doesn't read past what's been published
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            sender.send(6);
+            sender
+        });
+        out.clear();
+        assert_eq!(receiver.batch_recv(&mut out), 1);
+        assert_eq!(out, vec![6]);
+        let mut sender = writer.join().expect("writer panicked");
+        sender.send(7);
+        assert_eq!(receiver.recv(), 7);
+    }
+
+    #[test]
+    fn batch_recv_whole_ring() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(0);
+        sender.send(1);
+        receiver
+            .fast_forward(Sequence(2))
+            .expect("couldn't fast forward");
+        // A full ring's worth starting half way through it
+        for i in 2..6 {
+            sender.send(i);
+        }
+        let mut out = Vec::new();
+        assert_eq!(receiver.batch_recv(&mut out), 4);
+        assert_eq!(out, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn batch_recv_max() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut out = Vec::new();
+        assert_eq!(receiver.batch_recv_max(&mut out, 0), 0);
+        for i in 0..6 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.batch_recv_max(&mut out, 4), 4);
+        assert_eq!(out, vec![0, 1, 2, 3]);
+        assert_eq!(receiver.batch_recv_max(&mut out, 100), 2);
+        assert_eq!(out, vec![0, 1, 2, 3, 4, 5]);
+        sender.send(6);
+        assert_eq!(receiver.recv_with_seq(), (Sequence(6), 6));
+    }
+
+    #[test]
+    fn batch_recv_max_full_ring_across_seam() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        for i in 0..3 {
+            sender.send(i);
+        }
+        // Holding the next slot rather than the last one read so the whole ring fits
+        receiver
+            .fast_forward(Sequence(3))
+            .expect("couldn't fast forward");
+        for i in 3..11 {
+            sender.send(i);
+        }
+        let mut out = Vec::new();
+        assert_eq!(receiver.batch_recv_max(&mut out, 100), 8);
+        assert_eq!(out, (3..11).collect::<Vec<_>>());
+        // Only the last slot read is held now
+        for i in 11..18 {
+            sender.send(i);
+        }
+        out.clear();
+        assert_eq!(receiver.batch_recv_max(&mut out, 100), 7);
+        assert_eq!(out, (11..18).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn batch_recv_max_doesnt_pass_other_receivers() {
+        let (mut sender, mut fast) = channel(8).expect("couldn't create channel").dissolve();
+        for i in 0..4 {
+            sender.send(i);
+        }
+        let mut slow = fast.clone();
+        assert_eq!(slow.recv(), 0);
+        let mut out = Vec::new();
+        assert_eq!(fast.batch_recv_max(&mut out, 4), 4);
+        // The slow receiver is holding 0 so only 7 more fit
+        for i in 4..8 {
+            sender.send(i);
+        }
+        for i in 1..8 {
+            assert_eq!(slow.recv(), i);
+        }
+    }
+
+    #[test]
+    fn try_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert!(matches!(receiver.try_recv(), Err(ReceiverError::NoNewData)));
+        sender.send(1);
+        sender.send(2);
+        assert_eq!(receiver.try_recv().expect("couldn't receive"), 1);
+        assert_eq!(receiver.recv(), 2);
+        assert!(matches!(receiver.try_recv(), Err(ReceiverError::NoNewData)));
+        // Failing doesn't move the receiver
+        sender.send(3);
+        assert_eq!(receiver.try_recv().expect("couldn't receive"), 3);
+    }
+
+    #[test]
+    fn recv_borrowed() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        sender.send(String::from("hello"));
+        sender.send(String::from("world"));
+        assert_eq!(receiver.recv_borrowed().expect("couldn't receive"), "hello");
+        let value = receiver.recv_borrowed().expect("couldn't receive");
+        assert_eq!(value, "world");
+        let evictor = std::thread::spawn(move || sender.evict_slowest());
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        // The value is still borrowed so the receiver can't be evicted yet
+        assert!(!evictor.is_finished());
+        assert_eq!(value, "world");
+        assert!(matches!(receiver.try_recv(), Err(ReceiverError::NoNewData)));
+        let id = receiver.id();
+        assert_eq!(evictor.join().expect("evictor panicked"), Some(id));
+    }
+
+    #[test]
+    fn async_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(0);
+        assert!(matches!(
+            crate::utils::block_on(receiver.async_recv()),
+            Ok(0)
+        ));
+        let writer = std::thread::spawn(move || {
+            for i in 1..10 {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                sender.send(i);
+            }
+        });
+        for i in 1..10 {
+            assert_eq!(
+                crate::utils::block_on(receiver.async_recv()).expect("couldn't receive"),
+                i
+            );
+        }
+        writer.join().expect("writer panicked");
+        assert!(matches!(
+            crate::utils::block_on(receiver.async_recv()),
+            Err(ReceiverError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn recv_timeout() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let timeout = std::time::Duration::from_millis(20);
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            receiver.recv_timeout(timeout),
+            Err(ReceiverError::Timeout)
+        ));
+        assert!(start.elapsed() >= timeout);
+        // Timing out didn't move the receiver on
+        sender.send(1);
+        sender.send(2);
+        assert_eq!(receiver.recv_timeout(timeout).expect("couldn't receive"), 1);
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            sender.send(3);
+        });
+        assert_eq!(receiver.recv(), 2);
+        assert_eq!(
+            receiver
+                .recv_timeout(std::time::Duration::from_secs(5))
+                .expect("couldn't receive"),
+            3
+        );
+        writer.join().expect("writer panicked");
+        assert!(matches!(
+            receiver.recv_timeout(timeout),
+            Err(ReceiverError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn recv_latest_or_wait() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        for i in 0..5 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv_latest_or_wait().expect("couldn't receive"), 4);
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            sender.send(5);
+            sender
+        });
+        // Caught up so it waits
+        assert_eq!(receiver.recv_latest_or_wait().expect("couldn't receive"), 5);
+        drop(writer.join().expect("writer panicked"));
+        assert!(matches!(
+            receiver.recv_latest_or_wait(),
+            Err(ReceiverError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn audit() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert_eq!(unsafe { receiver.audit() }.count(), 0);
+        for i in 0..6 {
+            sender.send(i);
+            receiver.recv();
+        }
+        let seen: Vec<_> = unsafe { receiver.audit() }.copied().collect();
+        assert_eq!(seen, vec![2, 3, 4, 5]);
+        // Nothing was read
+        sender.send(6);
+        assert_eq!(receiver.recv(), 6);
+    }
+
+    #[test]
+    fn batch_recv_checked() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let mut out = Vec::new();
+        sender.send(0);
+        sender.send(1);
+        assert_eq!(receiver.batch_recv_checked(&mut out), Ok(2));
+        for i in 2..5 {
+            sender.send(i);
+        }
+        assert_eq!(sender.evict_slowest(), Some(receiver.id()));
+        for i in 5..10 {
+            sender.send(i);
+        }
+        // 2 to 8 are skipped and it picks up again at the latest
+        assert_eq!(
+            receiver.batch_recv_checked(&mut out),
+            Err(OverrunError { lost: 7 })
+        );
+        assert_eq!(receiver.batch_recv_checked(&mut out), Ok(1));
+        assert_eq!(out, vec![0, 1, 9]);
+    }
+
+    #[test]
+    fn recv_cow() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(String::from("kept"));
+        sender.send(String::from("looked at"));
+        let kept = receiver.recv_cow().expect("couldn't receive").into_owned();
+        assert!(matches!(
+            receiver.recv_cow().expect("couldn't receive"),
+            std::borrow::Cow::Borrowed(value) if value == "looked at"
+        ));
+        assert_eq!(kept, "kept");
+    }
+
+    #[test]
+    fn is_disconnected() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let other_sender = sender.clone();
+        sender.send(1);
+        drop(sender);
+        assert!(!receiver.is_disconnected());
+        drop(other_sender);
+        // There's still something to read
+        assert!(!receiver.is_disconnected());
+        assert_eq!(receiver.recv(), 1);
+        assert!(receiver.is_disconnected());
+        let mut sender = BroadcastSender::from(receiver.clone());
+        assert!(!receiver.is_disconnected());
+        sender.send(2);
+        drop(sender);
+        assert!(!receiver.is_disconnected());
+        assert_eq!(receiver.recv(), 2);
+        assert!(receiver.is_disconnected());
+    }
+
+    #[test]
+    fn transfer() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        for i in 0..4 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv(), 0);
+        let id = receiver.id();
+        let token = receiver.transfer();
+        let stats = sender.stats();
+        assert_eq!(stats.receiver_positions(), vec![(id, 0)]);
+        let reader = std::thread::spawn(move || {
+            let mut receiver = token.claim();
+            assert_eq!(receiver.id(), id);
+            (1..4).map(|_| receiver.recv()).collect::<Vec<_>>()
+        });
+        assert_eq!(reader.join().expect("reader panicked"), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn peek_ahead() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut out = Vec::new();
+        assert_eq!(receiver.peek_ahead(3, &mut out), 0);
+        for i in 0..6 {
+            sender.send(i);
+            assert_eq!(receiver.recv(), i);
+        }
+        // This wraps around the end of the ring
+        for i in 6..12 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.peek_ahead(4, &mut out), 4);
+        assert_eq!(out, vec![6, 7, 8, 9]);
+        out.clear();
+        assert_eq!(receiver.peek_ahead(100, &mut out), 6);
+        assert_eq!(out, (6..12).collect::<Vec<_>>());
+        assert_eq!(receiver.recv(), 6);
+        receiver.detach();
+        assert_eq!(receiver.peek_ahead(100, &mut out), 0);
+    }
+
+    #[test]
+    fn peek_latest() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert_eq!(receiver.peek_latest(), None);
+        for i in 0..3 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.peek_latest(), Some(2));
+        // Nothing was read
+        assert_eq!(receiver.recv(), 0);
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.recv(), 1);
+        assert_eq!(receiver.recv(), 2);
+        // The receiver is holding the last one it read
+        assert_eq!(receiver.peek_latest(), Some(2));
+        sender.send(3);
+        assert_eq!(receiver.peek_latest(), Some(3));
+        receiver.detach();
+        assert_eq!(receiver.peek_latest(), None);
+    }
+
+    #[test]
+    fn batch_buffer() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut batch = BatchBuffer::new(3);
+        assert!(batch.try_recv_into(&mut receiver).is_empty());
+        for i in 0..8 {
+            sender.send(i.to_string());
+        }
+        let allocation = batch.values.as_ptr();
+        assert_eq!(batch.recv_into(&mut receiver), ["0", "1", "2"]);
+        assert_eq!(batch.try_recv_into(&mut receiver), ["3", "4", "5"]);
+        assert_eq!(batch.recv_into(&mut receiver), ["6", "7"]);
+        assert!(batch.try_recv_into(&mut receiver).is_empty());
+        assert_eq!(batch.values.as_ptr(), allocation);
+    }
+
+    #[test]
+    fn try_recv_batch() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut out = Vec::new();
+        assert_eq!(receiver.try_recv_batch(&mut out), 0);
+        for i in 0..4 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.try_recv_batch(&mut out), 4);
+        assert_eq!(receiver.try_recv_batch(&mut out), 0);
+        for i in 4..7 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.try_recv_batch(&mut out), 3);
+        assert_eq!(out, (0..7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn recv_with_heartbeat() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(1);
+        let mut beats = 0;
+        let interval = std::time::Duration::from_millis(5);
+        assert_eq!(
+            receiver
+                .recv_with_heartbeat(interval, || beats += 1)
+                .expect("couldn't receive"),
+            1
+        );
+        assert_eq!(beats, 0);
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            sender.send(2);
+        });
+        assert_eq!(
+            receiver
+                .recv_with_heartbeat(interval, || beats += 1)
+                .expect("couldn't receive"),
+            2
+        );
+        assert!(beats >= 2);
+        writer.join().expect("writer panicked");
+    }
+
+    #[test]
+    fn consume_batch() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut sum = 0;
+        let mut calls = 0;
+        assert_eq!(receiver.consume_batch(|_| calls += 1), 0);
+        assert_eq!(calls, 0);
+        for i in 0..5 {
+            sender.send(i);
+        }
+        assert_eq!(
+            receiver.consume_batch(|values| sum += values.iter().sum::<i32>()),
+            5
+        );
+        assert_eq!(sum, 10);
+        // This run wraps around the end of the ring
+        for i in 5..12 {
+            sender.send(i);
+        }
+        let mut runs = Vec::new();
+        assert_eq!(
+            receiver.consume_batch(|values| runs.push(values.to_vec())),
+            7
+        );
+        assert_eq!(runs, vec![vec![5, 6, 7], vec![8, 9, 10, 11]]);
+        assert_eq!(receiver.consume_batch(|_| calls += 1), 0);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn recv_changed() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let reader = std::thread::spawn(move || {
+            let mut changes = Vec::new();
+            while changes.last() != Some(&0) {
+                changes.push(receiver.recv_changed().expect("couldn't receive"));
+            }
+            changes
+        });
+        // Far more repeats than fit in the channel
+        for value in [1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 1, 1, 1, 1, 1, 1, 0] {
+            sender.send(value);
+        }
+        assert_eq!(reader.join().expect("reader panicked"), vec![1, 2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn ack_through() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let tail =
+            |receiver: &BroadcastReceiver<usize>| receiver.get_core().reader_tracker().current();
+        receiver.set_deferred_acks(true);
+        for i in 0..4 {
+            sender.send(i);
+        }
+        let mut batch = Vec::new();
+        assert_eq!(receiver.try_recv_batch(&mut batch), 4);
+        // Nothing has been acknowledged so the senders still can't have any of it back
+        assert_eq!(tail(&receiver), 0);
+        receiver.ack_through(Sequence(2)).expect("not evicted");
+        assert_eq!(tail(&receiver), 2);
+        sender.send(4);
+        sender.send(5);
+        assert_eq!(receiver.recv(), 4);
+        assert_eq!(receiver.recv(), 5);
+        assert_eq!(tail(&receiver), 2);
+        receiver.ack_through(Sequence(5)).expect("not evicted");
+        assert_eq!(tail(&receiver), 5);
+        receiver.set_deferred_acks(false);
+        sender.send(6);
+        assert_eq!(receiver.recv(), 6);
+        assert_eq!(tail(&receiver), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ack_through_unread() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        receiver.set_deferred_acks(true);
+        sender.send(0);
+        sender.send(1);
+        receiver.recv();
+        let _ = receiver.ack_through(Sequence(1));
+    }
+
+    #[test]
+    fn recv_detailed() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(0);
+        sender.send(1);
+        let info = receiver.recv_detailed().expect("not evicted");
+        assert_eq!(
+            info,
+            RecvInfo {
+                value: 0,
+                sequence: Sequence(0),
+                pending: 1,
+                blocked: false,
+            }
+        );
+        assert_eq!(receiver.recv_detailed().expect("not evicted").pending, 0);
+        let reader = std::thread::spawn(move || receiver.recv_detailed().expect("not evicted"));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        sender.send(2);
+        let info = reader.join().expect("reader panicked");
+        assert_eq!(
+            (info.value, info.sequence, info.blocked),
+            (2, Sequence(2), true)
+        );
+    }
+
+    #[test]
+    fn recv_with_seq() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        for i in 0..10 {
+            sender.send(i * 2);
+            assert_eq!(receiver.recv_with_seq(), (Sequence(i), i * 2));
+        }
+        let mut late = receiver.add_stream().expect("couldn't add stream");
+        sender.send(20);
+        // Sequences are positions in the stream so they're the same for every receiver
+        assert_eq!(late.recv_with_seq(), (Sequence(9), 18));
+        assert_eq!(late.recv_with_seq(), (Sequence(10), 20));
+        assert_eq!(receiver.recv_with_seq(), (Sequence(10), 20));
+    }
+
+    #[test]
+    fn fast_forward_full_ring() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        for i in 0..4 {
+            sender.send(i);
+        }
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(100))
+                .expect("couldn't fast forward"),
+            4
+        );
+        sender.send(4);
+        assert_eq!(receiver.recv(), 4);
+
+        // Another receiver is holding the oldest message so it can only get to the latest
+        let mut slow = receiver.clone();
+        for i in 5..8 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv(), 5);
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(100))
+                .expect("couldn't fast forward"),
+            1
+        );
+        assert_eq!(receiver.recv(), 7);
+        // The clone started where the receiver was
+        for i in 5..8 {
+            assert_eq!(slow.recv(), i);
+        }
+    }
+
+    #[test]
+    fn fast_forward() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        for i in 0..6 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv(), 0);
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(4))
+                .expect("couldn't fast forward"),
+            3
+        );
+        assert_eq!(receiver.recv(), 4);
+        // Can't go backwards
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(1))
+                .expect("couldn't fast forward"),
+            0
+        );
+        assert_eq!(receiver.recv(), 5);
+        // Can't go past the next message to be sent
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(100))
+                .expect("couldn't fast forward"),
+            0
+        );
+        // Nothing is holding the tail back other than where we've moved to
+        for i in 6..13 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.recv(), 6);
+    }
+
+    #[test]
+    fn rewind() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut other = receiver.clone();
+        for i in 0..6 {
+            sender.send(i);
+        }
+        for i in 0..4 {
+            assert_eq!(receiver.recv(), i);
+        }
+        assert_eq!(other.recv(), 0);
+        assert_eq!(other.recv(), 1);
+        assert_eq!(receiver.rewind(2).expect("couldn't rewind"), 2);
+        assert_eq!(receiver.recv(), 2);
+        assert_eq!(receiver.recv(), 3);
+        assert_eq!(receiver.recv(), 4);
+        // The other receiver is still holding 1 so that's as far back as we can go
+        assert_eq!(receiver.rewind(10).expect("couldn't rewind"), 4);
+        assert_eq!(receiver.recv(), 1);
+        drop(other);
+        assert_eq!(receiver.rewind(0).expect("couldn't rewind"), 0);
+        for i in 2..6 {
+            assert_eq!(receiver.recv(), i);
+        }
+        // Only the last message read is still held so that's all that can be read again
+        for i in 6..13 {
+            sender.send(i);
+        }
+        for i in 6..13 {
+            assert_eq!(receiver.recv(), i);
+        }
+        assert_eq!(receiver.rewind(5).expect("couldn't rewind"), 1);
+        assert_eq!(receiver.recv(), 12);
+    }
+
+    #[test]
+    fn fast_forward_detached() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut other = receiver.clone();
+        receiver.detach();
+        for i in 0..30 {
+            sender.send(i);
+            assert_eq!(other.recv(), i);
+        }
+        assert!(matches!(
+            receiver.fast_forward(Sequence(2)),
+            Err(ReceiverError::Lagged)
+        ));
+        assert!(receiver.is_detached());
+        // The other receiver is holding the tail at 29 so anything before that can be overwritten
+        assert!(matches!(
+            receiver.fast_forward(Sequence(25)),
+            Err(ReceiverError::Lagged)
+        ));
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(29))
+                .expect("couldn't fast forward"),
+            29
+        );
+        assert!(!receiver.is_detached());
+        assert_eq!(receiver.recv(), 29);
+
+        // With no other receivers the senders could have overwritten anything
+        drop(other);
+        receiver.detach();
+        for i in 30..60 {
+            sender.send(i);
+        }
+        assert!(matches!(
+            receiver.fast_forward(Sequence(40)),
+            Err(ReceiverError::Lagged)
+        ));
+        assert_eq!(
+            receiver
+                .fast_forward(Sequence(59))
+                .expect("couldn't fast forward"),
+            29
+        );
+        assert_eq!(receiver.recv(), 59);
+    }
+
+    #[test]
+    fn snapshot() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert!(receiver.snapshot().is_empty());
+        // Wrap the ring a few times so the snapshot crosses the seam
+        for i in 0..10 {
+            sender.send(i);
+            assert_eq!(receiver.recv(), i);
+        }
+        for i in 10..13 {
+            sender.send(i);
+        }
+        assert_eq!(receiver.snapshot(), vec![10, 11, 12]);
+        assert_eq!(receiver.recv(), 10);
+        assert_eq!(receiver.snapshot(), vec![11, 12]);
+
+        receiver.detach();
+        assert!(receiver.snapshot().is_empty());
+    }
+
+    #[test]
+    fn read_entire_buffer() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        // A fresh receiver is holding slot 0 before it's read it so exactly capacity fits
+        for i in 0..8 {
+            sender.send(i);
+        }
+        for i in 0..8 {
+            assert_eq!(receiver.recv(), i);
+        }
+        // The receiver still holds the last slot it read so one less fits from here on
+        for i in 8..15 {
+            sender.send(i);
+        }
+        for i in 8..15 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn wrapping() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        // Offset by one each round so the seam lands in a different place every time
+        let mut next_send = 0;
+        let mut next_recv = 0;
+        for round in 1..24 {
+            for _ in 0..(round % 7) + 1 {
+                sender.send(next_send);
+                next_send += 1;
+            }
+            while next_recv < next_send {
+                assert_eq!(receiver.recv(), next_recv);
+                next_recv += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn full_buffer_blocks_sender() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let writer = std::thread::spawn(move || {
+            for i in 0..5 {
+                sender.send(i);
+            }
+        });
+        std::thread::sleep(core::time::Duration::from_millis(50));
+        // The 5th message can't fit until the receiver has read the first one
+        assert!(!writer.is_finished());
+        assert_eq!(receiver.recv(), 0);
+        assert_eq!(receiver.recv(), 1);
+        writer.join().expect("writer panicked");
+        for i in 2..5 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn failed_add_stream_doesnt_leak() {
+        let (mut sender, mut receiver) = channel(2).expect("couldn't create channel").dissolve();
+        let core = sender.get_core();
+        let done = Arc::new(core::sync::atomic::AtomicBool::new(false));
+        let writer = {
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let mut i = 0;
+                while !done.load(Ordering::Relaxed) {
+                    sender.send(i);
+                    i += 1;
+                }
+                sender
+            })
+        };
+        let reader = {
+            let done = done.clone();
+            let mut receiver = receiver.clone();
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    receiver.try_recv().ok();
+                }
+            })
+        };
+        // Only the reader holds back the writer
+        receiver.detach();
+        // Whether each one succeeds or not depends on how far the channel moves while it joins
+        for _ in 0..2000 {
+            drop(receiver.add_stream());
+        }
+        done.store(true, Ordering::Relaxed);
+        let sender = writer.join().expect("writer panicked");
+        reader.join().expect("reader panicked");
+        // Nothing is left holding back the senders
+        assert_eq!(sender.receiver_count(), 0);
+        assert_eq!(
+            crate::channel::wait_strategy::Waitable::current_value(&core.reader_tracker()),
+            isize::MAX
+        );
+    }
+
+    #[test]
+    fn add_stream_reads_latest() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        for i in 0..3 {
+            sender.send(i);
+        }
+        let mut late = receiver.add_stream().expect("couldn't add stream");
+        assert_eq!(late.recv(), 2);
+        for i in 0..3 {
+            assert_eq!(receiver.recv(), i);
+        }
+        for i in 3..10 {
+            sender.send(i);
+            assert_eq!(late.recv(), i);
+            assert_eq!(receiver.recv(), i);
+        }
+    }
 }
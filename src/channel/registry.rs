@@ -0,0 +1,333 @@
+//! Keeps track of the broadcast receivers attached to a channel so that a sender can find one
+//! that's holding everyone up and evict it.
+//!
+//! A receiver reads values in place while it holds its slot in the reader tracker. Evicting it
+//! mid read would let a sender overwrite the value it's reading. To avoid that a receiver marks
+//! itself busy whenever it's using its position and an evictor waits for it to be idle before
+//! removing it from the reader tracker. Marking itself idle again is a plain store so a read only
+//! pays for one read-modify-write.
+//!
+//! Each time a receiver attaches it gets a fresh state. Once a state has been evicted or detached
+//! it stays that way so an evictor that's still holding on to it can't remove the receiver twice.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use super::tracker::ReceiverTracker;
+
+const IDLE: u8 = 0;
+const BUSY: u8 = 1;
+const EVICTED: u8 = 2;
+const DETACHED: u8 = 3;
+
+/// The part of a receiver that's visible to the rest of the channel
+#[derive(Debug)]
+pub(crate) struct ReceiverState {
+    id: usize,
+    // The position the receiver holds in the reader tracker. Only meaningful while it's listed
+    position: AtomicIsize,
+    // The receiver hasn't read the value at its position yet. A new receiver holds the slot
+    // before the first value it's going to read
+    unread: AtomicBool,
+    status: AtomicU8,
+    // On a channel with credit, the first id the receiver hasn't let the senders send
+    credit: AtomicIsize,
+}
+
+impl ReceiverState {
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn position(&self) -> isize {
+        self.position.load(Ordering::Acquire)
+    }
+
+    /// Only call this while busy
+    pub(crate) fn set_position(&self, at: isize) {
+        self.position.store(at, Ordering::Release);
+    }
+
+    /// Set whether the receiver still has to read the value at its position
+    pub(crate) fn set_unread(&self, unread: bool) {
+        // SeqCst to pair with Ring::draining
+        self.unread.store(unread, Ordering::SeqCst);
+    }
+
+    pub(crate) fn credit(&self) -> isize {
+        self.credit.load(Ordering::Acquire)
+    }
+
+    /// Let the senders send `n` more messages
+    pub(crate) fn grant_credit(&self, n: isize) {
+        self.credit.fetch_add(n, Ordering::AcqRel);
+    }
+
+    /// Has the receiver read everything up to and including `id`
+    fn read_through(&self, id: isize) -> bool {
+        let position = self.position();
+        position > id || (position == id && !self.unread.load(Ordering::SeqCst))
+    }
+
+    /// Mark the receiver as using its position. Returns false if it's been evicted or detached.
+    /// The receiver no longer holds a position in the reader tracker if that's the case
+    pub(crate) fn begin(&self) -> bool {
+        let previous = self.status.swap(BUSY, Ordering::AcqRel);
+        if previous == EVICTED || previous == DETACHED {
+            // Evictors never change a state that's left busy so there's no race in putting it back
+            self.status.store(previous, Ordering::Release);
+            return false;
+        }
+        true
+    }
+
+    /// Mark the receiver as done with its position
+    pub(crate) fn end(&self) {
+        // Only the receiver moves out of busy so there's nothing to race with
+        self.status.store(IDLE, Ordering::Release);
+    }
+
+    /// Mark the receiver as done with its position if it's still busy. Unlike [end](Self::end)
+    /// this is safe to call when it might not be
+    pub(crate) fn release(&self) {
+        let _ = self
+            .status
+            .compare_exchange(BUSY, IDLE, Ordering::AcqRel, Ordering::Relaxed);
+    }
+
+    /// Mark the receiver as having left the reader tracker by itself. Only call this while busy
+    pub(crate) fn end_detached(&self) {
+        self.status.store(DETACHED, Ordering::Release);
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ReceiverRegistry {
+    receivers: Mutex<Vec<Arc<ReceiverState>>>,
+    next_id: AtomicUsize,
+    // Notified whenever a receiver is listed
+    attached: event_listener::Event,
+}
+
+impl ReceiverRegistry {
+    /// Room for `receivers` to be listed before the list needs to grow
+    pub(crate) fn with_capacity(receivers: usize) -> Self {
+        Self {
+            receivers: Mutex::new(Vec::with_capacity(receivers)),
+            next_id: Default::default(),
+            attached: Default::default(),
+        }
+    }
+
+    fn next_id(&self) -> usize {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// List a new receiver that has just registered in the reader tracker at `at`. `unread` is
+    /// whether it still has to read the value at `at`. It starts without any credit
+    pub(crate) fn new_receiver(&self, at: isize, unread: bool) -> Arc<ReceiverState> {
+        let next = if unread { at } else { at + 1 };
+        self.rejoin(self.next_id(), at, unread, next)
+    }
+
+    /// The state for a new receiver that isn't in the reader tracker
+    pub(crate) fn new_detached(&self) -> Arc<ReceiverState> {
+        Arc::new(ReceiverState {
+            id: self.next_id(),
+            position: AtomicIsize::new(-1),
+            unread: AtomicBool::new(false),
+            status: AtomicU8::new(DETACHED),
+            credit: AtomicIsize::new(-1),
+        })
+    }
+
+    /// List an existing receiver that has registered in the reader tracker again at `at`.
+    /// `credit` is the first id it hasn't let the senders send
+    pub(crate) fn rejoin(
+        &self,
+        id: usize,
+        at: isize,
+        unread: bool,
+        credit: isize,
+    ) -> Arc<ReceiverState> {
+        let receiver = Arc::new(ReceiverState {
+            id,
+            position: AtomicIsize::new(at),
+            unread: AtomicBool::new(unread),
+            status: AtomicU8::new(IDLE),
+            credit: AtomicIsize::new(credit),
+        });
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(receiver.clone());
+        self.attached.notify(usize::MAX);
+        receiver
+    }
+
+    pub(crate) fn remove(&self, id: usize) {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|receiver| receiver.id != id);
+    }
+
+    /// How many receivers are listed
+    pub(crate) fn len(&self) -> usize {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Start listening for the next receiver to be listed. The listener can be awaited
+    pub(crate) fn listen_attached(&self) -> event_listener::EventListener {
+        self.attached.listen()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .capacity()
+    }
+
+    /// The id and position of every listed receiver
+    pub(crate) fn positions(&self) -> Vec<(usize, isize)> {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|receiver| (receiver.id, receiver.position()))
+            .collect()
+    }
+
+    /// Has every listed receiver read everything up to and including `id`
+    pub(crate) fn read_through(&self, id: isize) -> bool {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .all(|receiver| receiver.read_through(id))
+    }
+
+    /// The first id that not every listed receiver has let the senders send. None if there
+    /// aren't any receivers
+    pub(crate) fn min_credit(&self) -> Option<isize> {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|receiver| receiver.credit())
+            .min()
+    }
+
+    /// The id and position of the receiver furthest behind
+    pub(crate) fn slowest(&self) -> Option<(usize, isize)> {
+        self.receivers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|receiver| (receiver.id, receiver.position()))
+            .min_by_key(|(_, position)| *position)
+    }
+
+    /// Remove the receiver furthest behind from the reader tracker returning its id.
+    /// Waits for the receiver to finish if it's in the middle of a read
+    pub(crate) fn evict_slowest(&self, tracker: &impl ReceiverTracker) -> Option<usize> {
+        'next_slowest: loop {
+            let receiver = {
+                let mut receivers = self
+                    .receivers
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                let (index, _) = receivers
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, receiver)| receiver.position())?;
+                // Don't hold the lock while waiting. The receiver might be trying to detach
+                receivers.remove(index)
+            };
+            loop {
+                match receiver.status.compare_exchange_weak(
+                    IDLE,
+                    EVICTED,
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => break,
+                    // It left by itself while we were waiting
+                    Err(DETACHED) => continue 'next_slowest,
+                    Err(_) => std::thread::yield_now(),
+                }
+            }
+            tracker.de_register(receiver.position());
+            return Some(receiver.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+    use crate::channel::tracker::{MultiCursorTracker, Tracker};
+    use crate::channel::wait_strategy::BusyWait;
+
+    #[test]
+    fn evict_idle() {
+        let tracker = MultiCursorTracker::new(8, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        let registry = ReceiverRegistry::default();
+        assert_eq!(registry.evict_slowest(&tracker), None);
+
+        let slow = registry.new_receiver(tracker.register(0).expect("couldn't register"), true);
+        let fast = registry.new_receiver(tracker.register(0).expect("couldn't register"), true);
+        assert!(fast.begin());
+        tracker.update(0, 3);
+        fast.set_position(3);
+        fast.end();
+
+        assert_eq!(registry.evict_slowest(&tracker), Some(slow.id()));
+        assert_eq!(tracker.current(), 3);
+        assert!(!slow.begin());
+        assert_eq!(registry.evict_slowest(&tracker), Some(fast.id()));
+        assert!(!fast.begin());
+        assert_eq!(registry.evict_slowest(&tracker), None);
+    }
+
+    #[test]
+    fn detached_while_evicting() {
+        use std::sync::Arc;
+        let tracker = Arc::new(
+            MultiCursorTracker::new(8, BusyWait::default())
+                .expect("couldn't create multi cursor tracker"),
+        );
+        let registry = Arc::new(ReceiverRegistry::default());
+        let other = registry.new_receiver(tracker.register(0).expect("couldn't register"), true);
+        tracker.update(0, 2);
+        other.set_position(2);
+        let leaving = registry.new_receiver(tracker.register(2).expect("couldn't register"), true);
+        other.set_position(3);
+        tracker.update(2, 3);
+
+        // The evictor picks `leaving` but it's busy
+        assert!(leaving.begin());
+        let evictor = {
+            let tracker = tracker.clone();
+            let registry = registry.clone();
+            std::thread::spawn(move || registry.evict_slowest(&*tracker))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        registry.remove(leaving.id());
+        tracker.de_register(2);
+        leaving.end_detached();
+
+        // It moves on to the next slowest rather than removing `leaving` a second time
+        assert_eq!(evictor.join().expect("evictor panicked"), Some(other.id()));
+        assert_eq!(tracker.current(), 3);
+        assert!(!leaving.begin());
+    }
+}
@@ -1,18 +1,22 @@
 use alloc::sync::Arc;
-use core::mem::forget;
+use alloc::vec::Vec;
 use std::sync::atomic::{fence, Ordering};
+use std::time::{Duration, Instant};
 
 use super::tracker::{ProducerTracker, Tracker};
+use super::wait_strategy::{WaitStrategy, Waitable};
 use super::Core;
 use crate::channel::Ring;
-use crate::utils::FastMod;
-use crate::BroadcastReceiver;
+use crate::utils::validate_ordering;
+use crate::{BroadcastReceiver, ChannelStats, ReceiverError, ReceiverFactory, Sequence};
 
 #[derive(Debug)]
 pub enum SenderError {
     /// The given input is too large to fit in the buffered channel
     InputTooLarge,
     ChannelFull,
+    /// The channel wasn't built with [evictable](crate::ChannelBuilder::evictable) set
+    NotEvictable,
 }
 
 pub trait Sender<T: Send>: Clone {
@@ -25,15 +29,40 @@ pub trait Sender<T: Send>: Clone {
 pub struct BroadcastSender<T> {
     core: Arc<Ring<T>>,
     capacity: isize,
+    // See ChannelBuilder::high_watermark
+    high_watermark: isize,
+    low_watermark: isize,
     cached_tail: isize,
+    // Set once this sender has claimed past the first lap. Ids only go up so every slot has a
+    // value from then on and sends can always replace
+    wrapped: bool,
+    // The least credit every receiver had granted when it was last checked. A receiver that
+    // joins later doesn't take back what's been seen here
+    cached_credit: isize,
+}
+
+impl<T> BroadcastSender<Arc<T>> {
+    /// Send a value on a [shared](crate::channel_shared) channel. It's put in an [Arc] once
+    /// here and every receiver gets a clone of the [Arc]
+    pub fn send_shared(&mut self, value: T) {
+        self.send(Arc::new(value))
+    }
 }
 
 impl<T> Clone for BroadcastSender<T> {
     fn clone(&self) -> Self {
+        // The tail only ever moves forward so anything it's been seen at is safe to start from.
+        // Starting at 0 would make the clone's first send wait on the tracker for no reason
+        let cached_tail = self.cached_tail.max(self.core.reader_tracker().current());
+        self.core.add_sender();
         Self {
             core: self.core.clone(),
             capacity: self.capacity,
-            cached_tail: 0,
+            high_watermark: self.high_watermark,
+            low_watermark: self.low_watermark,
+            cached_tail,
+            wrapped: self.wrapped,
+            cached_credit: self.cached_credit,
         }
     }
 }
@@ -41,10 +70,28 @@ impl<T> Clone for BroadcastSender<T> {
 impl<T> From<Arc<Ring<T>>> for BroadcastSender<T> {
     fn from(disruptor: Arc<Ring<T>>) -> Self {
         let capacity = disruptor.capacity() as isize;
+        let (high_watermark, low_watermark) = disruptor.watermarks();
+        disruptor.add_sender();
         Self {
             core: disruptor,
             capacity,
+            high_watermark: high_watermark as isize,
+            low_watermark: low_watermark as isize,
             cached_tail: 0,
+            wrapped: false,
+            cached_credit: 0,
+        }
+    }
+}
+
+impl<T> Drop for BroadcastSender<T> {
+    fn drop(&mut self) {
+        if self.core.remove_sender() == 0 {
+            // Receivers waiting for a message that's never coming can find out they're
+            // disconnected
+            self.core.sender_tracker().wait_strategy().notify();
+        } else {
+            self.core.sender_tracker().flush();
         }
     }
 }
@@ -58,14 +105,242 @@ impl<T> From<BroadcastReceiver<T>> for BroadcastSender<T> {
 impl<T> BroadcastSender<T> {
     fn claim(&mut self) -> isize {
         let claimed = self.core.sender_tracker().make_claim();
+        self.wait_for_space(claimed);
+        claimed
+    }
 
-        let tail = claimed - self.capacity;
+    /// Claim `n` sequential ids returning the first
+    fn claim_n(&mut self, n: usize) -> isize {
+        debug_assert!(n > 0);
+        let first = self.core.sender_tracker().make_claims(n);
+        self.wait_for_space(first + n as isize - 1);
+        first
+    }
+
+    /// Wait until the slot for `claimed` is no longer needed by any reader
+    #[inline(always)]
+    fn wait_for_space(&mut self, claimed: isize) {
+        let tail = claimed - self.high_watermark;
         if tail >= 0 && self.cached_tail <= tail {
-            self.cached_tail = self.core.reader_tracker().wait_for(tail + 1);
+            // The receivers we might wait for could be waiting for us
+            self.core.sender_tracker().flush();
+            // Once it's full wait for it to drain down to the low watermark. That's the same as
+            // waiting for one slot when neither has been set
+            let reader_tail = self
+                .core
+                .reader_tracker()
+                .wait_for(claimed - self.low_watermark + 1);
+            // Without any receivers nothing stops this lapping a sender that's still writing
+            // the slot's last message. Receivers never hold anything that hasn't been published
+            // so this only waits when there aren't any
+            let published = self
+                .core
+                .sender_tracker()
+                .wait_published(claimed - self.capacity);
+            self.cached_tail = reader_tail.min(published + 1);
         }
         debug_assert!(tail < 0 || self.cached_tail > tail);
+        validate_ordering!(
+            (&self.core.reader_tracker()).current_value() > tail,
+            "claimed {claimed} while a reader is still holding {tail}"
+        );
+        if self.core.credited() && self.cached_credit <= claimed {
+            self.wait_for_credit(claimed);
+        }
+    }
 
-        claimed
+    /// Wait until every receiver has granted credit for `claimed`
+    #[cold]
+    fn wait_for_credit(&mut self, claimed: isize) {
+        self.core.sender_tracker().flush();
+        let receivers = self.core.receivers();
+        let wait_strategy = self.core.reader_tracker().wait_strategy();
+        loop {
+            // Nobody to ask when there aren't any receivers
+            let credit = receivers.min_credit().unwrap_or(isize::MAX);
+            if credit > claimed {
+                self.cached_credit = credit;
+                return;
+            }
+            // Credit could be granted between checking and listening
+            let listener = wait_strategy.listen();
+            if receivers.min_credit().unwrap_or(isize::MAX) > claimed {
+                continue;
+            }
+            listener.wait();
+        }
+    }
+
+    /// How many messages could be sent right now without waiting for a receiver. Messages other
+    /// senders have claimed but not yet published aren't taken into account
+    fn free_space(&self) -> usize {
+        // This is effectively infinite when there are no receivers
+        let tail = (&self.core.reader_tracker()).current_value();
+        let next = self.core.sender_tracker().current() + 1;
+        tail.saturating_add(self.capacity)
+            .saturating_sub(next)
+            .clamp(0, self.capacity) as usize
+    }
+
+    /// How full the channel is from 0.0 to 1.0 as seen by the slowest receiver. It's 1.0 when a
+    /// send would have to wait and 0.0 when every receiver has caught up or there are none.
+    /// Messages other senders have claimed but not yet published aren't taken into account
+    pub fn utilization(&self) -> f32 {
+        // Once the slowest receiver has read something it holds on to that slot so at most
+        // capacity - 1 are ever free. A receiver that hasn't read anything yet can leave the
+        // whole channel free, which counts as empty too
+        let usable = (self.capacity as usize - 1).max(1);
+        let used = usable.saturating_sub(self.free_space());
+        used as f32 / usable as f32
+    }
+
+    /// Wait until at least `min` messages can be sent without blocking. Useful for collecting a
+    /// batch that's the right size for the channel before sending it.
+    ///
+    /// Once the slowest receiver has read something it holds on to the last slot it read, so
+    /// only a channel with no receivers or whose receivers haven't read anything yet can have
+    /// all `capacity` slots free. Asking for the whole channel returns straight away if it's
+    /// free and [SenderError::InputTooLarge] if it isn't, rather than waiting for something that
+    /// might never happen. Asking for more than that always returns
+    /// [SenderError::InputTooLarge]. Other senders can take the space before this sender gets
+    /// to use it.
+    pub async fn await_space(&self, min: usize) -> Result<(), SenderError> {
+        let capacity = self.capacity as usize;
+        if min > capacity || (min == capacity && self.free_space() < capacity) {
+            return Err(SenderError::InputTooLarge);
+        }
+        loop {
+            if self.free_space() >= min {
+                return Ok(());
+            }
+            let listener = self.core.reader_tracker().wait_strategy().listen();
+            // The tail might have moved before we started listening
+            if self.free_space() >= min {
+                return Ok(());
+            }
+            listener.await;
+        }
+    }
+
+    /// Has every attached receiver read everything that's been published
+    fn drained(&self) -> bool {
+        let published = self.core.sender_tracker().current();
+        published < 0 || self.core.receivers().read_through(published)
+    }
+
+    /// Wait until every attached receiver has read everything that's been published. Detached
+    /// receivers aren't waited for. Messages other senders publish in the meantime have to be
+    /// read too
+    pub async fn wait_drained(&self) {
+        let _waiting = self.core.wait_for_drain();
+        self.core.sender_tracker().flush();
+        loop {
+            if self.drained() {
+                return;
+            }
+            let listener = self.core.reader_tracker().wait_strategy().listen();
+            // A receiver might have caught up before we started listening
+            if self.drained() {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /// How many receivers are attached right now. [Detached](BroadcastReceiver::detach)
+    /// receivers aren't counted
+    pub fn receiver_count(&self) -> usize {
+        self.core.receivers().len()
+    }
+
+    /// Block until there's at least one receiver attached so that nothing is sent before anyone
+    /// is listening
+    pub fn wait_for_receiver(&self) {
+        while self.receiver_count() == 0 {
+            let listener = self.core.receivers().listen_attached();
+            // A receiver might have attached before we started listening
+            if self.receiver_count() > 0 {
+                return;
+            }
+            listener.wait();
+        }
+    }
+
+    /// Wait until there's at least one receiver attached without blocking
+    pub async fn wait_for_receiver_async(&self) {
+        while self.receiver_count() == 0 {
+            let listener = self.core.receivers().listen_attached();
+            // A receiver might have attached before we started listening
+            if self.receiver_count() > 0 {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /// Wake up receivers blocked on messages that were sent without waking them because of
+    /// [notify_granularity](crate::ChannelBuilder::notify_granularity). Call it when the sender
+    /// is going to stop sending for a while. Does nothing on other channels
+    pub fn flush(&self) {
+        self.core.sender_tracker().flush();
+    }
+
+    /// Has the channel been [closed](Self::close) or [shut down](Self::shutdown). Anything sent
+    /// after that might never be read
+    pub fn is_closed(&self) -> bool {
+        self.core.is_closed()
+    }
+
+    /// Close the channel straight away. Receivers still get everything that has already been
+    /// sent and then [ReceiverError::Disconnected] from [try_recv](BroadcastReceiver::try_recv)
+    /// and [Receiver::recv](crate::Receiver::recv).
+    /// Other senders can still send but what they send might never be read
+    pub fn close(self) {
+        self.core.close();
+    }
+
+    /// Send one last message and [close](Self::close) the channel. Every receiver gets `value`
+    /// before it's told the channel is disconnected, so it's a good place for a flush marker.
+    /// Other senders should be dropped first or what they send after it might never be read
+    pub fn send_final(mut self, value: T) {
+        self.send(value);
+        // Published before it's closed so a receiver that sees it closed can read it
+        self.close();
+    }
+
+    /// Shut the channel down gracefully. Waits for every receiver to read everything that's
+    /// been sent and then [closes](Self::close) the channel so receivers find out there's
+    /// nothing more coming. Drop or shut down the other senders first.
+    ///
+    /// There's no timer to give up on here. An async service that can't wait forever can race
+    /// [wait_drained](Self::wait_drained) against its runtime's timer and then close the
+    /// channel either way
+    pub async fn shutdown(self) {
+        self.wait_drained().await;
+        self.close();
+    }
+
+    /// The same as [shutdown](Self::shutdown) but it blocks and closes the channel anyway if the
+    /// receivers haven't read everything within `timeout`. Returns whether they did
+    pub fn shutdown_timeout(self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let waiting = self.core.wait_for_drain();
+        self.core.sender_tracker().flush();
+        let drained = loop {
+            if self.drained() {
+                break true;
+            }
+            let listener = self.core.reader_tracker().wait_strategy().listen();
+            if self.drained() {
+                break true;
+            }
+            if !listener.wait_deadline(deadline) && !self.drained() {
+                break false;
+            }
+        };
+        drop(waiting);
+        self.close();
+        drained
     }
 
     pub fn send(&mut self, value: T) {
@@ -73,34 +348,304 @@ impl<T> BroadcastSender<T> {
         self.internal_send(value, claimed_id)
     }
 
+    /// Send the value `fill` makes out of a value a receiver has handed back with
+    /// [return_buffer](crate::BroadcastReceiver::return_buffer), or out of None when there
+    /// isn't one. Once a few buffers are going round a producer with large buffers doesn't
+    /// have to allocate for each message.
+    ///
+    /// The returned value is owned by `fill` and whatever it sends is owned by the channel.
+    /// Receivers get their own clone so what comes back is the receiver's copy, not the one
+    /// that was sent, and with more than one receiver every message can come back more than once
+    ///
+    /// ```
+    /// let (mut sender, mut receiver) = nexusq::channel::<Vec<u8>>(4)
+    ///     .expect("couldn't create channel")
+    ///     .dissolve();
+    /// sender.send_with(|buffer| {
+    ///     let mut buffer = buffer.unwrap_or_else(|| Vec::with_capacity(1024));
+    ///     buffer.push(1);
+    ///     buffer
+    /// });
+    /// let mut message = receiver.recv();
+    /// message.clear();
+    /// receiver.return_buffer(message);
+    /// sender.send_with(|buffer| {
+    ///     let mut buffer = buffer.expect("the buffer was returned");
+    ///     assert!(buffer.is_empty());
+    ///     buffer.push(2);
+    ///     buffer
+    /// });
+    /// assert_eq!(receiver.recv(), vec![2]);
+    /// ```
+    pub fn send_with(&mut self, fill: impl FnOnce(Option<T>) -> T) {
+        let value = fill(self.core.take_buffer());
+        self.send(value)
+    }
+
+    /// Send a value and get back a handle that can be used to wait for every receiver to read it.
+    /// Useful for holding on to the source of a message until it's been consumed
+    pub fn send_acked(&mut self, value: T) -> MessageHandle<T> {
+        let claimed_id = self.claim();
+        self.internal_send(value, claimed_id);
+        MessageHandle {
+            core: self.core.clone(),
+            id: claimed_id,
+        }
+    }
+
+    /// Send a value calling `on_stall` while waiting for the senders ahead of this one to
+    /// publish, instead of spinning. It's called over and over until it's this sender's turn so
+    /// it should be quick. Waiting for a receiver to make room blocks the same as
+    /// [send](Self::send)
+    pub fn send_yielding(&mut self, value: T, on_stall: impl FnMut()) {
+        let claimed_id = self.claim();
+        let old_value = self.internal_send_with(value, claimed_id, |core, id| {
+            core.sender_tracker().publish_with(id, on_stall)
+        });
+        drop(old_value);
+    }
+
+    /// Send a value and get back the one it overwrote instead of dropping it. Useful for
+    /// recycling buffers rather than allocating a new one for every message. Returns None until
+    /// the channel has wrapped around and there's something to overwrite
+    pub fn send_replacing(&mut self, value: T) -> Option<T> {
+        let claimed_id = self.claim();
+        self.internal_send_with(value, claimed_id, |core, id| {
+            core.sender_tracker().publish(id)
+        })
+    }
+
     #[inline(always)]
     fn internal_send(&mut self, value: T, claimed_id: isize) {
+        let old_value = self.internal_send_with(value, claimed_id, |core, id| {
+            core.sender_tracker().publish(id)
+        });
+        // This will ensure that the compiler doesn't do this earlier for some reason (it probably wouldn't anyway)
+        drop(old_value);
+    }
+
+    #[inline(always)]
+    fn internal_send_with(
+        &mut self,
+        value: T,
+        claimed_id: isize,
+        publish: impl FnOnce(&Ring<T>, isize),
+    ) -> Option<T> {
         debug_assert!(claimed_id >= 0);
-        let index = claimed_id.pow_2_mod(self.capacity) as usize;
+        if !self.wrapped {
+            self.wrapped = claimed_id >= self.capacity;
+            if !self.wrapped {
+                return self.first_lap_send(value, claimed_id, publish);
+            }
+        }
 
-        let mut old_value: Option<T> = None;
+        let old_value;
         unsafe {
-            if claimed_id < self.capacity {
-                core::ptr::copy_nonoverlapping(
-                    &value,
-                    (*self.core.ring()).get_unchecked_mut(index),
-                    1,
-                );
-                forget(value)
-            } else {
-                old_value = Some(core::mem::replace(
-                    (*self.core.ring()).get_unchecked_mut(index),
-                    value,
-                ));
-            }
+            old_value = self.core.replace(claimed_id, value);
             fence(Ordering::Release)
         }
 
         // Notify other threads that a value has been written
-        self.core.sender_tracker().publish(claimed_id);
+        publish(&self.core, claimed_id);
+        self.core.beat();
+        Some(old_value)
+    }
 
-        // This will ensure that the compiler doesn't do this earlier for some reason (it probably wouldn't anyway)
-        drop(old_value);
+    /// Send into a slot that might not have been written to yet
+    #[cold]
+    fn first_lap_send(
+        &mut self,
+        value: T,
+        claimed_id: isize,
+        publish: impl FnOnce(&Ring<T>, isize),
+    ) -> Option<T> {
+        let old_value;
+        unsafe {
+            old_value = self.core.write(claimed_id, value);
+            fence(Ordering::Release)
+        }
+        publish(&self.core, claimed_id);
+        self.core.beat();
+        old_value
+    }
+
+    /// Send a batch of messages as one run. Receivers see all of them, in order, or none of them
+    /// and no other sender's messages end up in the middle.
+    ///
+    /// The slowest receiver always holds on to the last slot it read so a run can be at most
+    /// `capacity - 1` long. A longer batch would never fit and is handed back with
+    /// [SenderError::InputTooLarge]. Use [send_batch_chunked](Self::send_batch_chunked) to send
+    /// it in parts instead
+    pub fn send_batch(&mut self, mut values: Vec<T>) -> Result<(), (SenderError, Vec<T>)> {
+        if values.len() >= self.capacity as usize {
+            return Err((SenderError::InputTooLarge, values));
+        }
+        if !values.is_empty() {
+            self.send_run(values.drain(..));
+        }
+        Ok(())
+    }
+
+    /// Send a batch of messages of any size in runs of up to `capacity - 1`. Each run is sent
+    /// like [send_batch](Self::send_batch) but other senders' messages can end up between runs
+    pub fn send_batch_chunked(&mut self, mut values: Vec<T>) {
+        let max_run = (self.capacity as usize - 1).max(1);
+        while !values.is_empty() {
+            let run = values.len().min(max_run);
+            self.send_run(values.drain(..run));
+        }
+    }
+
+    /// Send a batch of messages as one run overwriting the oldest messages instead of waiting for
+    /// the receivers to read them. Receivers that haven't read the messages that are in the way
+    /// are [evicted](Self::evict_slowest), slowest first. Their next read fails with
+    /// [ReceiverError::Evicted] and [batch_recv_checked](BroadcastReceiver::batch_recv_checked)
+    /// says how many they lost. Anything before the newest `capacity - 1` messages would be
+    /// lapped by the rest of the batch before any receiver could see it so it's dropped up front.
+    /// Returns how many were dropped.
+    ///
+    /// Receivers read values in place so only an [evictable](crate::ChannelBuilder::evictable)
+    /// channel can have messages overwritten. On any other channel the batch is handed back with
+    /// [SenderError::NotEvictable]. Evicting a receiver waits for it to finish a read that's in
+    /// progress, and a receiver that attaches while room is being made is waited for as usual
+    pub fn send_batch_overwrite(
+        &mut self,
+        mut values: Vec<T>,
+    ) -> Result<usize, (SenderError, Vec<T>)> {
+        if !self.core.evictable() {
+            return Err((SenderError::NotEvictable, values));
+        }
+        let max_run = (self.capacity as usize - 1).max(1);
+        let dropped = values.len().saturating_sub(max_run);
+        if values.len() > dropped {
+            let run = values.drain(dropped..);
+            let first = self.core.sender_tracker().make_claims(run.len());
+            let last = first + run.len() as isize - 1;
+            self.make_room(last);
+            self.wait_for_space(last);
+            self.write_run(first, run);
+        }
+        Ok(dropped)
+    }
+
+    /// Evict the slowest receivers until `last` can be claimed without waiting for any of them
+    fn make_room(&self, last: isize) {
+        // The same room wait_for_space waits for
+        let tail = last - self.low_watermark;
+        while (&self.core.reader_tracker()).current_value() <= tail {
+            if self.evict_slowest().is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Claim, write and publish a run of messages in one go
+    fn send_run(&mut self, values: impl ExactSizeIterator<Item = T>) {
+        let first = self.claim_n(values.len());
+        self.write_run(first, values);
+    }
+
+    /// Write and publish a run of messages into ids that have already been claimed
+    fn write_run(&mut self, first: isize, values: impl ExactSizeIterator<Item = T>) {
+        let last = first + values.len() as isize - 1;
+        self.wrapped = self.wrapped || first >= self.capacity;
+        if !self.wrapped {
+            // The run might start in slots that have never been written
+            let old_values: Vec<_> = values
+                .enumerate()
+                .map(|(offset, value)| unsafe { self.core.write(first + offset as isize, value) })
+                .collect();
+            fence(Ordering::Release);
+            self.core.sender_tracker().publish_range(first, last);
+            self.core.beat();
+            drop(old_values);
+            return;
+        }
+        let mut old_values = Vec::with_capacity(values.len());
+        for (offset, value) in values.enumerate() {
+            unsafe {
+                old_values.push(self.core.replace(first + offset as isize, value));
+            }
+        }
+        fence(Ordering::Release);
+        self.core.sender_tracker().publish_range(first, last);
+        self.core.beat();
+        drop(old_values);
+    }
+
+    /// How many messages senders have claimed a slot for but haven't published yet. That
+    /// includes senders waiting for space and senders waiting for the ones ahead of them to
+    /// publish first. A number that stays high means the senders are contending with each other
+    pub fn inflight(&self) -> usize {
+        self.core.sender_tracker().inflight()
+    }
+
+    /// The sender's view of the channel as `(cached_tail, capacity)`. `cached_tail` is the
+    /// slowest receiver's position the last time this sender had to check. For tests only
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn debug_internals(&self) -> (isize, isize) {
+        (self.cached_tail, self.capacity)
+    }
+
+    /// The sequence the next message sent will have if no other sender claims it first. It's
+    /// only a hint when there's more than one sender.
+    /// [send_acked](Self::send_acked) gives the exact sequence of a message once it's sent
+    pub fn next_sequence(&self) -> Sequence {
+        Sequence::from_cursor(self.core.sender_tracker().next_claim())
+    }
+
+    /// The number of messages published to the channel since it was created by every sender.
+    /// Messages that have been claimed but not published yet aren't counted
+    pub fn total_sent(&self) -> u64 {
+        (self.core.sender_tracker().current() + 1) as u64
+    }
+
+    /// Evict the receiver that's furthest behind so it stops holding up the channel. Returns the
+    /// [id](BroadcastReceiver::id) of the evicted receiver or None if there aren't any.
+    /// The evicted receiver's next read fails with [ReceiverError::Evicted]. If the receiver is in
+    /// the middle of a read this waits for it to finish. That includes the closure passed to
+    /// [recv_map](BroadcastReceiver::recv_map).
+    ///
+    /// Only channels built with [evictable](crate::ChannelBuilder::evictable) set can evict
+    /// receivers. This always returns None otherwise.
+    pub fn evict_slowest(&self) -> Option<usize> {
+        if !self.core.evictable() {
+            return None;
+        }
+        self.core
+            .receivers()
+            .evict_slowest(self.core.reader_tracker())
+    }
+
+    /// The [id](BroadcastReceiver::id) and position of every attached receiver. The position is
+    /// the id of the slot the receiver is holding, which is the last one it read. This is a
+    /// snapshot. Receivers keep moving while it's being taken so they might not all be from the
+    /// same moment
+    pub fn receiver_positions(&self) -> Vec<(usize, isize)> {
+        self.core.receivers().positions()
+    }
+
+    /// Something that can create receivers on this channel later without holding up the senders
+    /// in the meantime
+    pub fn receiver_factory(&self) -> ReceiverFactory<T> {
+        self.core.clone().into()
+    }
+
+    /// A view of the channel's state for monitoring
+    pub fn stats(&self) -> ChannelStats<T> {
+        self.core.clone().into()
+    }
+
+    /// Creates a new receiver at the most recent entry in the stream
+    pub fn add_stream(&self) -> Result<BroadcastReceiver<T>, ReceiverError> {
+        self.core.clone().try_into()
+    }
+
+    /// Start again from the beginning of a channel that's been recycled
+    pub(crate) fn recycle(&mut self) {
+        self.cached_tail = 0;
+        self.wrapped = false;
+        self.cached_credit = 0;
     }
 
     pub(crate) fn get_core(&self) -> Arc<Ring<T>> {
@@ -108,6 +653,173 @@ impl<T> BroadcastSender<T> {
     }
 }
 
+/// A message sent with [BroadcastSender::send_acked]. The message is acked once the slowest
+/// receiver has moved past it.
+///
+/// Receivers hold on to the last message they read until they read the next one. The reader
+/// tracker can't tell a receiver that has read the message apart from one that's just attached
+/// at it so a message is only acked once every receiver has started reading the one after.
+/// If there are no receivers attached the message is always acked
+#[derive(Debug)]
+pub struct MessageHandle<T> {
+    core: Arc<Ring<T>>,
+    id: isize,
+}
+
+impl<T> MessageHandle<T> {
+    /// Where the message is in the stream
+    pub fn sequence(&self) -> Sequence {
+        Sequence::from_cursor(self.id)
+    }
+
+    /// Has every receiver moved past the message
+    pub fn is_acked(&self) -> bool {
+        (&self.core.reader_tracker()).current_value() > self.id
+    }
+
+    /// Block until every receiver has moved past the message. Returns straight away when there
+    /// aren't any receivers
+    pub fn wait_acked(&self) {
+        self.core.sender_tracker().flush();
+        // Waiting on the reader tracker with no receivers would mark the message as overwritten
+        // and a receiver that joins later would start after it
+        loop {
+            if self.is_acked() {
+                return;
+            }
+            let listener = self.core.reader_tracker().wait_strategy().listen();
+            // The tail might have moved before we started listening
+            if self.is_acked() {
+                return;
+            }
+            listener.wait();
+        }
+    }
+
+    /// Wait for every receiver to move past the message without blocking
+    pub async fn wait_acked_async(&self) {
+        self.core.sender_tracker().flush();
+        loop {
+            if self.is_acked() {
+                return;
+            }
+            let listener = self.core.reader_tracker().wait_strategy().listen();
+            // The tail might have moved before we started listening
+            if self.is_acked() {
+                return;
+            }
+            listener.await;
+        }
+    }
+}
+
+impl<T> BroadcastSender<T>
+where
+    T: Default,
+{
+    /// Reserve a run of `n` messages that can be filled in any order. Their ids are claimed
+    /// straight away, waiting for room like [send_batch](Self::send_batch), so each message's
+    /// [sequence](Reservation::sequence) is known up front. The run is published, in order, as
+    /// soon as the last one is [set](Reservation::set).
+    ///
+    /// Messages are published in the order they were claimed so anything other senders send
+    /// after the reservation is held up until it's complete. Don't wait on another sender
+    /// while holding one. A reservation that's dropped before it's filled publishes
+    /// `T::default()` in place of the messages that weren't set so it can't hold them up
+    /// forever. The slowest receiver always holds on to the last slot it read so a run can be
+    /// at most `capacity - 1` long. Anything longer returns [SenderError::InputTooLarge].
+    pub fn reserve(&mut self, n: usize) -> Result<Reservation<'_, T>, SenderError> {
+        if n >= self.capacity as usize {
+            return Err(SenderError::InputTooLarge);
+        }
+        let first = if n > 0 { self.claim_n(n) } else { 0 };
+        let mut values = Vec::with_capacity(n);
+        values.resize_with(n, || None);
+        Ok(Reservation {
+            sender: self,
+            first,
+            len: n,
+            values,
+            remaining: n,
+            fill: T::default,
+        })
+    }
+}
+
+/// A run of messages reserved with [BroadcastSender::reserve]
+#[derive(Debug)]
+pub struct Reservation<'a, T> {
+    sender: &'a mut BroadcastSender<T>,
+    first: isize,
+    len: usize,
+    values: Vec<Option<T>>,
+    remaining: usize,
+    // Stands in for messages that weren't set when the reservation is dropped
+    fill: fn() -> T,
+}
+
+impl<'a, T> Reservation<'a, T> {
+    /// Set the message at `index` into the run. Setting it again replaces the old value which is
+    /// returned. The whole run is sent once every message has been set.
+    ///
+    /// # Panics
+    /// Panics if `index` is outside of the run or the run has already been sent
+    pub fn set(&mut self, index: usize, value: T) -> Option<T> {
+        // Once sent the values have been taken so any index is out of range
+        assert!(
+            index < self.values.len(),
+            "index {index} is outside of the reservation or it has already been sent"
+        );
+        let old_value = self.values[index].replace(value);
+        if old_value.is_none() {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                self.send();
+            }
+        }
+        old_value
+    }
+
+    /// How many messages still need to be set before the run is sent
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Where the message at `index` will be in the stream
+    ///
+    /// # Panics
+    /// Panics if `index` is outside of the run
+    pub fn sequence(&self, index: usize) -> Sequence {
+        assert!(
+            index < self.len,
+            "index {index} is outside of the reservation"
+        );
+        Sequence::from_cursor(self.first + index as isize)
+    }
+
+    fn send(&mut self) {
+        let first = self.first;
+        let values = self
+            .values
+            .drain(..)
+            .map(|value| value.expect("every message in a reservation is set before it's sent"));
+        self.sender.write_run(first, values);
+    }
+}
+
+impl<'a, T> Drop for Reservation<'a, T> {
+    fn drop(&mut self) {
+        // The ids have been claimed so they have to be published for anything after them to be
+        if self.values.is_empty() {
+            return;
+        }
+        for value in self.values.iter_mut().filter(|value| value.is_none()) {
+            *value = Some((self.fill)());
+        }
+        self.send();
+    }
+}
+
 impl<T> Sender<T> for BroadcastSender<T>
 where
     T: Send,
@@ -129,4 +841,731 @@ mod sender_tests {
         let v = receiver.recv();
         assert_eq!(v, 42);
     }
+
+    #[test]
+    fn reserve_out_of_order() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        sender.send(0);
+        let mut reservation = sender.reserve(4).expect("couldn't reserve");
+        for (index, value) in [(2, 3), (0, 1), (3, 4)] {
+            assert!(reservation.set(index, value).is_none());
+        }
+        assert_eq!(reservation.remaining(), 1);
+        assert_eq!(reservation.set(2, 3), Some(3));
+        reservation.set(1, 2);
+        assert_eq!(reservation.remaining(), 0);
+        drop(reservation);
+        sender.send(5);
+        for i in 0..6 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn abandoned_reservation() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut reservation = sender.reserve(3).expect("couldn't reserve");
+        reservation.set(1, String::from("kept"));
+        drop(reservation);
+        // The messages that weren't set are published as the default so another sender isn't
+        // held up by the unfinished reservation
+        let mut other = sender.clone();
+        other.send(String::from("hello"));
+        for expected in ["", "kept", "", "hello"] {
+            assert_eq!(receiver.recv(), expected);
+        }
+    }
+
+    #[test]
+    fn reservation_is_claimed_up_front() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        sender.send(0);
+        let mut other = sender.clone();
+        let mut reservation = sender.reserve(2).expect("couldn't reserve");
+        assert_eq!(reservation.sequence(0), Sequence(1));
+        assert_eq!(reservation.sequence(1), Sequence(2));
+        // Sent after the reservation so it's published after it
+        let later = std::thread::spawn(move || other.send(3));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(!later.is_finished());
+        assert_eq!(receiver.try_recv().ok(), Some(0));
+        assert!(receiver.try_recv().is_err());
+        reservation.set(1, 2);
+        reservation.set(0, 1);
+        later.join().expect("sender panicked");
+        for i in 1..4 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "already been sent")]
+    fn set_after_sent() {
+        let mut sender = sender_only::<usize>(4).expect("couldn't create channel");
+        let mut reservation = sender.reserve(1).expect("couldn't reserve");
+        reservation.set(0, 1);
+        reservation.set(0, 2);
+    }
+
+    #[test]
+    fn reservation_too_large() {
+        let mut sender = sender_only::<usize>(4).expect("couldn't create channel");
+        assert!(matches!(sender.reserve(4), Err(SenderError::InputTooLarge)));
+        assert!(sender.reserve(3).is_ok());
+    }
+
+    #[test]
+    fn reservations_from_two_senders() {
+        let (sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for run in 0..50 {
+                        let mut reservation = sender.reserve(3).expect("couldn't reserve");
+                        for index in (0..3).rev() {
+                            reservation.set(index, (writer, run * 3 + index));
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut next = [0; 2];
+        for _ in 0..300 {
+            let (writer, value) = receiver.recv();
+            assert_eq!(next[writer], value);
+            next[writer] += 1;
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+    }
+
+    #[test]
+    fn send_batch() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let Err((SenderError::InputTooLarge, rejected)) = sender.send_batch(vec![0, 1, 2, 3])
+        else {
+            panic!("a batch the size of the channel can't fit");
+        };
+        assert_eq!(rejected, [0, 1, 2, 3]);
+        assert_eq!(sender.total_sent(), 0);
+        sender.send_batch(vec![0, 1, 2]).expect("the batch fits");
+        sender.send_batch(Vec::new()).expect("nothing to send");
+        for i in 0..3 {
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn send_batch_chunked() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let reader = std::thread::spawn(move || {
+            for i in 0..10 {
+                assert_eq!(receiver.recv(), i);
+            }
+        });
+        // Would never fit in one go
+        sender.send_batch_chunked((0..10).collect());
+        reader.join().expect("reader panicked");
+    }
+
+    #[test]
+    fn send_batch_overwrite() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(sender.send_batch_overwrite(vec![0, 1]).ok(), Some(0));
+        assert_eq!(receiver.recv(), 0);
+        assert_eq!(receiver.recv(), 1);
+        assert_eq!(sender.send_batch_overwrite((2..12).collect()).ok(), Some(7));
+        for i in 9..12 {
+            assert_eq!(receiver.recv(), i);
+        }
+        assert_eq!(sender.send_batch_overwrite(Vec::new()).ok(), Some(0));
+        assert_eq!(sender.total_sent(), 5);
+    }
+
+    #[test]
+    fn send_batch_overwrite_evicts_lapped_receivers() {
+        let (mut sender, mut slow) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let mut fast = slow.clone();
+        for i in 0..3 {
+            sender.send(i);
+            assert_eq!(fast.recv(), i);
+        }
+        assert_eq!(slow.recv(), 0);
+        // The slow receiver is holding 0 so there's only room for 3 more without overwriting.
+        // Sending 3 more would lap what it hasn't read
+        assert_eq!(sender.send_batch_overwrite(vec![3, 4, 5]).ok(), Some(0));
+        for i in 3..6 {
+            assert_eq!(fast.recv(), i);
+        }
+        // 1 was overwritten and it isn't holding back the rest any more
+        assert_eq!(slow.lag(), 4);
+        let mut out = Vec::new();
+        assert_eq!(
+            slow.batch_recv_checked(&mut out),
+            Err(OverrunError { lost: 4 })
+        );
+        assert_eq!(slow.recv(), 5);
+        // Nothing's in the way now
+        assert_eq!(sender.send_batch_overwrite(vec![6, 7]).ok(), Some(0));
+        assert_eq!(fast.recv(), 6);
+        assert_eq!(slow.recv(), 6);
+    }
+
+    #[test]
+    fn send_batch_overwrite_not_evictable() {
+        let (mut sender, _receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert!(matches!(
+            sender.send_batch_overwrite(vec![0, 1]),
+            Err((SenderError::NotEvictable, values)) if values == vec![0, 1]
+        ));
+        assert_eq!(sender.total_sent(), 0);
+    }
+
+    #[test]
+    fn evict_slowest() {
+        let (mut sender, mut stuck) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let mut reader = stuck.clone();
+        sender.send(0);
+        assert_eq!(reader.recv(), 0);
+        for i in 1..4 {
+            sender.send(i);
+            assert_eq!(reader.recv(), i);
+        }
+        assert_eq!(sender.evict_slowest(), Some(stuck.id()));
+        // Would block forever if the stuck receiver was still holding the tail
+        for i in 4..7 {
+            sender.send(i);
+            assert_eq!(reader.recv(), i);
+        }
+        assert!(matches!(
+            Receiver::recv(&mut stuck),
+            Err(ReceiverError::Evicted)
+        ));
+        assert!(stuck.is_detached());
+        // Reattached at the latest message
+        assert_eq!(stuck.recv(), 6);
+        sender.send(7);
+        assert_eq!(stuck.recv(), 7);
+
+        assert_eq!(sender.evict_slowest(), Some(reader.id()));
+        assert_eq!(sender.evict_slowest(), Some(stuck.id()));
+        assert_eq!(sender.evict_slowest(), None);
+    }
+
+    #[test]
+    fn not_evictable() {
+        let (sender, _receiver) = channel::<usize>(4)
+            .expect("couldn't create channel")
+            .dissolve();
+        assert_eq!(sender.evict_slowest(), None);
+    }
+
+    #[test]
+    fn evict_mid_read() {
+        use std::sync::{Arc, Barrier};
+        let (mut sender, mut receiver) = ChannelBuilder::new(4)
+            .evictable(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let id = receiver.id();
+        sender.send(String::from("hello"));
+        let barrier = Arc::new(Barrier::new(2));
+        let reader = {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                let value = receiver
+                    .recv_map(|value| {
+                        barrier.wait();
+                        // Give the sender a chance to try evicting us mid read
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                        value.clone()
+                    })
+                    .expect("the read started before the eviction");
+                assert_eq!(value, "hello");
+                assert!(matches!(
+                    Receiver::recv(&mut receiver),
+                    Err(ReceiverError::Evicted)
+                ));
+            })
+        };
+        barrier.wait();
+        // Waits for the read to finish
+        assert_eq!(sender.evict_slowest(), Some(id));
+        for i in 0..10 {
+            sender.send(i.to_string());
+        }
+        reader.join().expect("reader panicked");
+    }
+
+    #[test]
+    fn await_space() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert!(matches!(
+            utils::block_on(sender.await_space(5)),
+            Err(SenderError::InputTooLarge)
+        ));
+        // The receiver hasn't read anything so the whole channel is free
+        utils::block_on(sender.await_space(4)).expect("couldn't wait for space");
+        for i in 0..4 {
+            sender.send(i);
+        }
+        let reader = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            for i in 0..4 {
+                assert_eq!(receiver.recv(), i);
+            }
+            receiver
+        });
+        utils::block_on(sender.await_space(3)).expect("couldn't wait for space");
+        assert_eq!(sender.free_space(), 3);
+        // It's holding the last one it read now so the whole channel can't be free
+        assert!(matches!(
+            utils::block_on(sender.await_space(4)),
+            Err(SenderError::InputTooLarge)
+        ));
+        let _receiver = reader.join().expect("reader panicked");
+    }
+
+    #[test]
+    fn utilization() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        assert_eq!(sender.utilization(), 0.0);
+        for i in 0..8 {
+            sender.send(i);
+        }
+        assert_eq!(sender.utilization(), 1.0);
+        for _ in 0..4 {
+            receiver.recv();
+        }
+        assert_eq!(receiver.backlog(), 0.5);
+        // The receiver is holding the fourth message
+        assert_eq!(sender.utilization(), 4.0 / 7.0);
+        for _ in 0..4 {
+            receiver.recv();
+        }
+        assert_eq!(sender.utilization(), 0.0);
+        assert_eq!(receiver.backlog(), 0.0);
+        drop(receiver);
+        sender.send(8);
+        assert_eq!(sender.utilization(), 0.0);
+    }
+
+    #[test]
+    fn send_acked() {
+        let (mut sender, mut fast) = channel(8).expect("couldn't create channel").dissolve();
+        let mut slow = fast.clone();
+        let first = sender.send_acked(0);
+        let second = sender.send_acked(1);
+        sender.send(2);
+        assert_eq!(second.sequence(), Sequence(1));
+        for _ in 0..3 {
+            fast.recv();
+        }
+        assert!(!first.is_acked());
+        let reader = std::thread::spawn(move || {
+            for i in 0..3 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                assert_eq!(slow.recv(), i);
+            }
+        });
+        first.wait_acked();
+        utils::block_on(second.wait_acked_async());
+        assert!(second.is_acked());
+        reader.join().expect("reader panicked");
+        drop(fast);
+        // Nobody is left to read it
+        assert!(sender.send_acked(2).is_acked());
+    }
+
+    #[test]
+    fn wait_acked_without_receivers() {
+        let mut sender = sender_only(8).expect("couldn't create channel");
+        let handle = sender.send_acked(1);
+        handle.wait_acked();
+        // Waiting didn't push a receiver that joins afterwards past the message
+        let mut receiver = sender.add_stream().expect("couldn't add stream");
+        assert_eq!(receiver.recv(), 1);
+    }
+
+    #[test]
+    fn clone_keeps_tail() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        for i in 0..10 {
+            sender.send(i);
+            receiver.recv();
+        }
+        let clone = sender.clone();
+        assert_eq!(clone.cached_tail, 9);
+        drop(sender);
+        // Cloning a clone carries the tail over too
+        let mut clone = clone.clone();
+        clone.send(10);
+        assert_eq!(receiver.recv(), 10);
+    }
+
+    #[test]
+    fn wrapped() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut other_sender = sender.clone();
+        for i in 0..3 {
+            sender.send(i.to_string());
+            assert_eq!(receiver.recv(), i.to_string());
+        }
+        assert!(!sender.wrapped);
+        // A run that starts in the first lap and finishes in the second
+        sender
+            .send_batch(vec![String::from("3"), String::from("4")])
+            .expect("couldn't send batch");
+        assert!(!sender.wrapped);
+        assert_eq!(receiver.recv(), "3");
+        assert_eq!(receiver.recv(), "4");
+        other_sender.send(String::from("5"));
+        assert!(other_sender.wrapped);
+        assert!(other_sender.clone().wrapped);
+        sender
+            .send_batch(vec![String::from("6")])
+            .expect("couldn't send batch");
+        assert!(sender.wrapped);
+        assert_eq!(receiver.recv(), "5");
+        assert_eq!(receiver.recv(), "6");
+    }
+
+    #[test]
+    fn shutdown() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let reader = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            loop {
+                match Receiver::recv(&mut receiver) {
+                    Ok(value) => received.push(value),
+                    Err(ReceiverError::Disconnected) => return received,
+                    Err(error) => panic!("unexpected error {error}"),
+                }
+            }
+        });
+        for i in 0..10 {
+            sender.send(i);
+        }
+        crate::utils::block_on(sender.shutdown());
+        assert_eq!(
+            reader.join().expect("reader panicked"),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn wait_drained_first_read() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        crate::utils::block_on(sender.wait_drained());
+        sender.send(0);
+        assert!(!sender.drained());
+        // The receiver was already holding the slot it read so the tail doesn't move
+        assert_eq!(receiver.recv(), 0);
+        assert!(sender.drained());
+        let mut late = receiver.add_stream().expect("couldn't add stream");
+        assert!(!sender.drained());
+        assert_eq!(late.recv(), 0);
+        assert!(sender.drained());
+    }
+
+    #[test]
+    fn shutdown_timeout() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let other = sender.clone();
+        sender.send(1);
+        assert!(!sender.shutdown_timeout(std::time::Duration::from_millis(20)));
+        // Anything sent before the channel was closed can still be read
+        assert_eq!(receiver.try_recv().expect("there's a message"), 1);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(ReceiverError::Disconnected)
+        ));
+        assert!(receiver.is_disconnected());
+        assert!(other.shutdown_timeout(std::time::Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn total_sent() {
+        let (mut sender, _receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut other_sender = sender.clone();
+        assert_eq!(sender.total_sent(), 0);
+        sender.send(1);
+        other_sender.send(2);
+        assert_eq!(sender.total_sent(), 2);
+        assert_eq!(other_sender.total_sent(), 2);
+    }
+
+    #[test]
+    fn sender_only_doesnt_block() {
+        let mut sender = sender_only(4).expect("couldn't create channel");
+        for i in 0..20 {
+            sender.send(i);
+        }
+        let mut receiver = sender.add_stream().expect("couldn't add stream");
+        assert_eq!(receiver.recv(), 19);
+        sender.send(20);
+        assert_eq!(receiver.recv(), 20);
+    }
+
+    #[test]
+    fn sender_only_doesnt_lap_unpublished() {
+        use crate::channel::tracker::ProducerTracker;
+        use crate::channel::Core;
+        let sender = sender_only::<usize>(4).expect("couldn't create channel");
+        // Claimed but not written yet, like a sender that's been descheduled part way through
+        let first = sender.core.sender_tracker().make_claims(4);
+        let lapping = std::thread::spawn({
+            let mut sender = sender.clone();
+            move || {
+                let mut stalls = 0;
+                sender.send_yielding(4, || stalls += 1);
+                stalls
+            }
+        });
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(!lapping.is_finished());
+        for id in first..first + 4 {
+            unsafe { sender.core.write(id, id as usize) };
+        }
+        sender.core.sender_tracker().publish_range(first, first + 3);
+        // It waited before writing to slot 0 rather than writing and then waiting to publish
+        assert_eq!(lapping.join().expect("sender panicked"), 0);
+        let mut receiver = sender.add_stream().expect("couldn't add stream");
+        assert_eq!(receiver.recv(), 4);
+    }
+
+    #[test]
+    fn sender_only_senders_lapping() {
+        use std::sync::Arc;
+        let value = Arc::new(());
+        let sender = sender_only::<Arc<()>>(4).expect("couldn't create channel");
+        let senders: Vec<_> = (0..4)
+            .map(|_| {
+                let mut sender = sender.clone();
+                let value = value.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5000 {
+                        sender.send(value.clone());
+                    }
+                })
+            })
+            .collect();
+        for handle in senders {
+            handle.join().expect("sender panicked");
+        }
+        assert_eq!(sender.total_sent(), 20000);
+        drop(sender);
+        // Every value that was overwritten was dropped exactly once
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn wait_for_receiver() {
+        let sender = sender_only::<usize>(4).expect("couldn't create channel");
+        assert_eq!(sender.receiver_count(), 0);
+        let factory = sender.receiver_factory();
+        let waiting = std::thread::spawn({
+            let sender = sender.clone();
+            move || sender.wait_for_receiver()
+        });
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(!waiting.is_finished());
+        let mut receiver = factory.receiver().expect("couldn't create receiver");
+        waiting.join().expect("waiting thread panicked");
+        assert_eq!(sender.receiver_count(), 1);
+        crate::utils::block_on(sender.wait_for_receiver_async());
+        receiver.detach();
+        assert_eq!(sender.receiver_count(), 0);
+    }
+
+    #[test]
+    fn credit() {
+        let (mut sender, mut receiver) = ChannelBuilder::new(16)
+            .credit(true)
+            .build()
+            .expect("couldn't create channel")
+            .dissolve();
+        let mut other = receiver.clone();
+        receiver.grant_credit(5);
+        other.grant_credit(2);
+        let writer = std::thread::spawn(move || {
+            for i in 0..5 {
+                sender.send(i);
+            }
+            sender
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // Only as far as the receiver with the least credit
+        let mut read = Vec::new();
+        assert_eq!(receiver.try_recv_batch(&mut read), 2);
+        assert!(!writer.is_finished());
+        other.grant_credit(3);
+        let sender = writer.join().expect("writer panicked");
+        assert_eq!(sender.total_sent(), 5);
+        read.clear();
+        assert_eq!(receiver.try_recv_batch(&mut read), 3);
+        assert_eq!(read, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn next_sequence() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        assert_eq!(sender.next_sequence(), Sequence::from(0));
+        sender.send(1);
+        let expected = sender.next_sequence();
+        sender.send(2);
+        receiver.recv();
+        assert_eq!(
+            receiver.recv_detailed().expect("couldn't receive").sequence,
+            expected
+        );
+        assert_eq!(sender.next_sequence(), Sequence::from(2));
+    }
+
+    #[test]
+    fn send_final() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let mut other = receiver.clone();
+        let _other_sender = sender.clone();
+        sender.send(1);
+        sender.send_final(2);
+        for receiver in [&mut receiver, &mut other] {
+            assert_eq!(receiver.try_recv().expect("couldn't receive"), 1);
+            assert_eq!(receiver.try_recv().expect("couldn't receive"), 2);
+            assert!(matches!(
+                receiver.try_recv(),
+                Err(ReceiverError::Disconnected)
+            ));
+        }
+    }
+
+    #[test]
+    fn send_with() {
+        let (mut sender, mut receiver) = channel::<Vec<usize>>(4)
+            .expect("couldn't create channel")
+            .dissolve();
+        for _ in 0..6 {
+            receiver.return_buffer(Vec::with_capacity(8));
+        }
+        // Only as many as there are slots are kept
+        let mut reused = 0;
+        for i in 0..6 {
+            sender.send_with(|buffer| {
+                reused += buffer.is_some() as usize;
+                let mut buffer = buffer.unwrap_or_default();
+                buffer.push(i);
+                buffer
+            });
+            assert_eq!(receiver.recv(), vec![i]);
+        }
+        assert_eq!(reused, 4);
+    }
+
+    #[test]
+    fn send_replacing() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        for i in 0..4 {
+            assert_eq!(sender.send_replacing(vec![i]), None);
+            assert_eq!(receiver.recv(), vec![i]);
+        }
+        for i in 4..10 {
+            assert_eq!(sender.send_replacing(vec![i]), Some(vec![i - 4]));
+            assert_eq!(receiver.recv(), vec![i]);
+        }
+    }
+
+    #[test]
+    fn send_yielding() {
+        use super::{Core, ProducerTracker};
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut stalls = 0;
+        // Nothing ahead of this sender so it never has to wait
+        sender.send_yielding(1, || stalls += 1);
+        assert_eq!(stalls, 0);
+        assert_eq!(receiver.recv(), 1);
+        // Claimed but not published so the next send has to wait for it
+        let core = sender.get_core();
+        let claimed = core.sender_tracker().make_claim();
+        let writer = std::thread::spawn(move || {
+            let mut stalls = 0;
+            sender.send_yielding(3, || {
+                stalls += 1;
+                std::thread::yield_now();
+            });
+            stalls
+        });
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        unsafe {
+            core.write(claimed, 2);
+        }
+        core.sender_tracker().publish(claimed);
+        assert!(writer.join().expect("writer panicked") > 0);
+        assert_eq!(receiver.recv(), 2);
+        assert_eq!(receiver.recv(), 3);
+    }
+
+    #[test]
+    fn receiver_positions() {
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel").dissolve();
+        let mut detached = receiver.clone();
+        detached.detach();
+        let other = receiver.clone();
+        for i in 0..4 {
+            sender.send(i);
+        }
+        receiver.recv();
+        receiver.recv();
+        let mut positions = sender.receiver_positions();
+        positions.sort();
+        assert_eq!(positions, vec![(receiver.id(), 1), (other.id(), 0)]);
+    }
+
+    #[test]
+    fn sender_count() {
+        let (sender, mut receiver) = channel::<usize>(4)
+            .expect("couldn't create channel")
+            .dissolve();
+        let core = sender.get_core();
+        assert_eq!(core.senders(), 1);
+        let clones: Vec<_> = (0..3).map(|_| sender.clone()).collect();
+        let from_receiver: BroadcastSender<usize> = receiver.clone().into();
+        assert_eq!(core.senders(), 5);
+        drop(clones);
+        assert_eq!(core.senders(), 2);
+        drop(from_receiver);
+        assert!(!receiver.is_disconnected());
+        // The last sender going wakes up a receiver that's waiting
+        let reader = std::thread::spawn(move || Receiver::recv(&mut receiver));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(sender);
+        assert_eq!(core.senders(), 0);
+        assert!(matches!(
+            reader.join().expect("reader panicked"),
+            Err(ReceiverError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn receiver_only() {
+        let mut receiver = crate::receiver_only(4).expect("couldn't create channel");
+        let mut sender: BroadcastSender<i32> = receiver.clone().into();
+        sender.send(42);
+        assert_eq!(receiver.recv(), 42);
+    }
 }
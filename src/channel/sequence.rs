@@ -0,0 +1,50 @@
+use core::fmt;
+
+/// The position of a message in the stream. The first message sent to a channel is sequence 0
+/// and every message after that is one more than the last. This is not an index into the ring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Sequence(pub u64);
+
+impl From<u64> for Sequence {
+    fn from(sequence: u64) -> Self {
+        Self(sequence)
+    }
+}
+
+impl From<Sequence> for u64 {
+    fn from(sequence: Sequence) -> Self {
+        sequence.0
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Sequence {
+    pub(crate) fn from_cursor(cursor: isize) -> Self {
+        debug_assert!(cursor >= 0);
+        Self(cursor as u64)
+    }
+}
+
+#[cfg(test)]
+mod sequence_tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use alloc::string::ToString;
+
+    #[test]
+    fn ordered_keys() {
+        let mut reorder = BTreeMap::new();
+        reorder.insert(Sequence::from(3), "c");
+        reorder.insert(Sequence::from(1), "a");
+        reorder.insert(Sequence::from(2), "b");
+        let values: Vec<_> = reorder.into_values().collect();
+        assert_eq!(values, vec!["a", "b", "c"]);
+        assert_eq!(Sequence(42).to_string(), "42");
+        assert_eq!(u64::from(Sequence(7)), 7);
+    }
+}
@@ -0,0 +1,340 @@
+//! A single producer single consumer channel that lives in memory provided by the caller, such
+//! as a shared memory mapping, so that the sender and receiver can be in different processes.
+//!
+//! Nothing in the region points anywhere else. The layout is fixed so any process that maps the
+//! same memory can open it. Offsets are in bytes from the start of the region
+//!
+//! | offset | size               | contents                                                   |
+//! |--------|--------------------|------------------------------------------------------------|
+//! | 0      | 8                  | magic number, written last by [ShmSender::create]          |
+//! | 8      | 8                  | size of `T` in bytes                                       |
+//! | 16     | 8                  | capacity in messages, a power of 2                         |
+//! | 64     | pointer sized      | id of the last published message, -1 before the first one  |
+//! | 128    | pointer sized      | id of the last message read, -1 before the first one       |
+//! | 192    | capacity * size(T) | the messages                                               |
+//!
+//! Everything is native endian. `T` must be [Copy] and mean the same thing in every process,
+//! so no pointers or references. Waiting is done by spinning and yielding as there's no way to
+//! block on another process.
+//!
+//! Only one sender and one receiver can use a region at a time. Nothing stops another process
+//! from opening a second one so that's up to the caller.
+
+use core::sync::atomic::{AtomicIsize, AtomicU64, Ordering};
+
+use super::wait_strategy::{WaitStrategy, YieldWait};
+use super::ChannelError;
+
+/// How many bytes come before the first message in a region
+pub const HEADER_SIZE: usize = 192;
+
+const MAGIC: u64 = u64::from_ne_bytes(*b"nexusq01");
+
+#[repr(C, align(64))]
+struct Line<V>(V);
+
+#[repr(C)]
+struct Meta {
+    magic: AtomicU64,
+    slot_size: u64,
+    capacity: u64,
+}
+
+// The sender and receiver write their cursors on different cache lines
+#[repr(C)]
+struct Header {
+    meta: Line<Meta>,
+    published: Line<AtomicIsize>,
+    consumed: Line<AtomicIsize>,
+}
+
+const _: () = assert!(core::mem::size_of::<Header>() == HEADER_SIZE);
+
+/// How big a region needs to be to hold `capacity` messages
+pub fn required_size<T>(capacity: usize) -> usize {
+    HEADER_SIZE + capacity * core::mem::size_of::<T>()
+}
+
+/// A region that's been checked to be usable
+#[derive(Debug)]
+struct Region<T> {
+    header: *const u8,
+    slots: *mut T,
+    capacity: isize,
+}
+
+impl<T: Copy> Region<T> {
+    /// # Safety
+    /// `ptr` must be valid for reads and writes of `size` bytes for as long as it's in use
+    unsafe fn new(ptr: *mut u8, size: usize) -> Result<Self, ChannelError> {
+        if core::mem::size_of::<T>() == 0
+            || core::mem::align_of::<T>() > 64
+            || ptr.align_offset(64) != 0
+        {
+            return Err(ChannelError::IncompatibleLayout);
+        }
+        if size < required_size::<T>(1) {
+            return Err(ChannelError::RegionTooSmall);
+        }
+        Ok(Self {
+            header: ptr,
+            slots: ptr.add(HEADER_SIZE).cast(),
+            capacity: 0,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*self.header.cast::<Header>() }
+    }
+
+    /// # Safety
+    /// The same as [new](Self::new)
+    unsafe fn open(ptr: *mut u8, size: usize) -> Result<Self, ChannelError> {
+        let mut region = Self::new(ptr, size)?;
+        let meta = &region.header().meta.0;
+        if meta.magic.load(Ordering::Acquire) != MAGIC
+            || meta.slot_size != core::mem::size_of::<T>() as u64
+            || !meta.capacity.is_power_of_two()
+            || meta.capacity > isize::MAX as u64
+        {
+            return Err(ChannelError::IncompatibleLayout);
+        }
+        if size < required_size::<T>(meta.capacity as usize) {
+            return Err(ChannelError::RegionTooSmall);
+        }
+        region.capacity = meta.capacity as isize;
+        Ok(region)
+    }
+
+    fn published(&self) -> &AtomicIsize {
+        &self.header().published.0
+    }
+
+    fn consumed(&self) -> &AtomicIsize {
+        &self.header().consumed.0
+    }
+
+    fn slot(&self, id: isize) -> *mut T {
+        unsafe { self.slots.add(id as usize & (self.capacity as usize - 1)) }
+    }
+}
+
+/// Sends messages through a shared memory region
+#[derive(Debug)]
+pub struct ShmSender<T> {
+    region: Region<T>,
+    next: isize,
+    cached_consumed: isize,
+    wait: YieldWait,
+}
+
+// Only `Copy` values that don't point anywhere are ever moved through the region
+unsafe impl<T: Copy + Send> Send for ShmSender<T> {}
+
+impl<T: Copy> ShmSender<T> {
+    /// Set up a new empty channel in the region and return its sender. The capacity is the
+    /// largest power of 2 that fits. Anything that was in the region is lost.
+    ///
+    /// # Safety
+    /// `ptr` must be aligned to 64 bytes and valid for reads and writes of `size` bytes for as
+    /// long as the sender, or any receiver opened on the region, is in use. Nothing else can be
+    /// using the region while it's being created
+    pub unsafe fn create(ptr: *mut u8, size: usize) -> Result<Self, ChannelError> {
+        let mut region = Region::<T>::new(ptr, size)?;
+        let fits = ((size - HEADER_SIZE) / core::mem::size_of::<T>()).min(isize::MAX as usize);
+        // The largest power of 2 that's no bigger than what fits
+        let capacity = 1 << (usize::BITS - 1 - fits.leading_zeros());
+        let header = ptr.cast::<Header>();
+        core::ptr::addr_of_mut!((*header).meta.0.slot_size).write(core::mem::size_of::<T>() as u64);
+        core::ptr::addr_of_mut!((*header).meta.0.capacity).write(capacity as u64);
+        region.published().store(-1, Ordering::Relaxed);
+        region.consumed().store(-1, Ordering::Relaxed);
+        // Anyone opening the region only trusts it once this is there
+        region.header().meta.0.magic.store(MAGIC, Ordering::Release);
+        region.capacity = capacity as isize;
+        Ok(Self::in_region(region))
+    }
+
+    /// Take over sending on a region that has already been [created](Self::create). Sending
+    /// carries on from the last message that was published.
+    ///
+    /// # Safety
+    /// The same as [create](Self::create) except that the region is already in use. There can't
+    /// be another sender on the region
+    pub unsafe fn open(ptr: *mut u8, size: usize) -> Result<Self, ChannelError> {
+        Ok(Self::in_region(Region::open(ptr, size)?))
+    }
+
+    fn in_region(region: Region<T>) -> Self {
+        let next = region.published().load(Ordering::Acquire) + 1;
+        Self {
+            region,
+            next,
+            cached_consumed: -1,
+            wait: Default::default(),
+        }
+    }
+
+    /// How many messages the channel can hold
+    pub fn capacity(&self) -> usize {
+        self.region.capacity as usize
+    }
+
+    /// Send a value. This blocks while the channel is full
+    pub fn send(&mut self, value: T) {
+        let id = self.next;
+        // The slot is free once the message that was in it has been read
+        let needed = id - self.region.capacity;
+        if self.cached_consumed < needed {
+            self.cached_consumed = self.wait.wait_for_geq(self.region.consumed(), needed);
+        }
+        unsafe {
+            self.region.slot(id).write(value);
+        }
+        self.region.published().store(id, Ordering::Release);
+        self.next += 1;
+    }
+}
+
+/// Receives messages through a shared memory region
+#[derive(Debug)]
+pub struct ShmReceiver<T> {
+    region: Region<T>,
+    next: isize,
+    cached_published: isize,
+    wait: YieldWait,
+}
+
+unsafe impl<T: Copy + Send> Send for ShmReceiver<T> {}
+
+impl<T: Copy> ShmReceiver<T> {
+    /// Start receiving from a region that has been [created](ShmSender::create). Receiving
+    /// carries on from the last message that was read.
+    ///
+    /// # Safety
+    /// `ptr` must be aligned to 64 bytes and valid for reads and writes of `size` bytes for as
+    /// long as the receiver is in use. There can't be another receiver on the region
+    pub unsafe fn open(ptr: *mut u8, size: usize) -> Result<Self, ChannelError> {
+        let region = Region::open(ptr, size)?;
+        let next = region.consumed().load(Ordering::Acquire) + 1;
+        Ok(Self {
+            region,
+            next,
+            cached_published: next - 1,
+            wait: Default::default(),
+        })
+    }
+
+    /// Read the next value, waiting for it to be sent if needed
+    pub fn recv(&mut self) -> T {
+        if self.cached_published < self.next {
+            self.cached_published = self.wait.wait_for_geq(self.region.published(), self.next);
+        }
+        self.take()
+    }
+
+    /// Read the next value if it's been sent without waiting
+    pub fn try_recv(&mut self) -> Option<T> {
+        if self.cached_published < self.next {
+            self.cached_published = self.region.published().load(Ordering::Acquire);
+            if self.cached_published < self.next {
+                return None;
+            }
+        }
+        Some(self.take())
+    }
+
+    fn take(&mut self) -> T {
+        let value = unsafe { self.region.slot(self.next).read() };
+        // The value has been copied out so the sender can have the slot back
+        self.region.consumed().store(self.next, Ordering::Release);
+        self.next += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod shm_tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[repr(C, align(64))]
+    #[derive(Clone, Copy)]
+    struct Block([u8; 64]);
+
+    /// Stands in for a shared memory mapping
+    fn region(size: usize) -> Vec<Block> {
+        alloc::vec![Block([0xAA; 64]); size.div_ceil(64)]
+    }
+
+    #[test]
+    fn send_recv() {
+        // Room for a bit more than 8 which gets rounded down
+        let size = required_size::<u64>(8) + 10;
+        let mut memory = region(size);
+        let ptr = memory.as_mut_ptr().cast::<u8>();
+        let mut sender = unsafe { ShmSender::<u64>::create(ptr, size) }.expect("couldn't create");
+        assert_eq!(sender.capacity(), 8);
+        let mut receiver = unsafe { ShmReceiver::<u64>::open(ptr, size) }.expect("couldn't open");
+        assert_eq!(receiver.try_recv(), None);
+        // The memory is only borrowed by the threads
+        let reader = std::thread::scope(|scope| {
+            let reader =
+                scope.spawn(move || (0..1000).map(|_| receiver.recv()).collect::<Vec<_>>());
+            for i in 0..1000 {
+                sender.send(i);
+            }
+            reader.join().expect("reader panicked")
+        });
+        assert_eq!(reader, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn reopen() {
+        let mut memory = region(required_size::<u32>(4));
+        let ptr = memory.as_mut_ptr().cast::<u8>();
+        let size = memory.len() * 64;
+        {
+            let mut sender =
+                unsafe { ShmSender::<u32>::create(ptr, size) }.expect("couldn't create");
+            sender.send(1);
+            sender.send(2);
+        }
+        {
+            let mut receiver =
+                unsafe { ShmReceiver::<u32>::open(ptr, size) }.expect("couldn't open");
+            assert_eq!(receiver.recv(), 1);
+        }
+        let mut sender = unsafe { ShmSender::<u32>::open(ptr, size) }.expect("couldn't open");
+        sender.send(3);
+        let mut receiver = unsafe { ShmReceiver::<u32>::open(ptr, size) }.expect("couldn't open");
+        assert_eq!(receiver.recv(), 2);
+        assert_eq!(receiver.recv(), 3);
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[test]
+    fn incompatible() {
+        let mut memory = region(required_size::<u64>(4));
+        let ptr = memory.as_mut_ptr().cast::<u8>();
+        let size = memory.len() * 64;
+        // Nothing has been created yet
+        assert!(matches!(
+            unsafe { ShmReceiver::<u64>::open(ptr, size) },
+            Err(ChannelError::IncompatibleLayout)
+        ));
+        unsafe { ShmSender::<u64>::create(ptr, size) }.expect("couldn't create");
+        assert!(matches!(
+            unsafe { ShmReceiver::<u32>::open(ptr, size) },
+            Err(ChannelError::IncompatibleLayout)
+        ));
+        assert!(matches!(
+            unsafe { ShmReceiver::<u64>::open(ptr, HEADER_SIZE) },
+            Err(ChannelError::RegionTooSmall)
+        ));
+        assert!(matches!(
+            unsafe { ShmReceiver::<u64>::open(ptr.add(8), size - 8) },
+            Err(ChannelError::IncompatibleLayout)
+        ));
+    }
+}
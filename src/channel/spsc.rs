@@ -0,0 +1,262 @@
+//! A single producer single consumer channel.
+//!
+//! There's exactly one [SpscSender] and one [SpscReceiver] and neither can be cloned. Knowing
+//! that, the trackers don't need any read-modify-write atomics. Claiming, publishing and moving
+//! the tail are all plain loads and release stores.
+//!
+//! When a second sender turns out to be needed after all the pair can be upgraded with
+//! [SpscSender::into_mpsc].
+
+use alloc::sync::Arc;
+use std::sync::atomic::{fence, Ordering};
+
+use super::tracker::{
+    ProducerTracker, ReceiverTracker, SingleCursorTracker, SingleProducerTracker, Tracker,
+};
+use super::wait_strategy::SpinBlockWait;
+use super::{checked_buffer_size, ChannelError, ChannelHandles, Core, Ring, Sequence};
+
+type SpscRing<T> =
+    Ring<T, SingleProducerTracker<SpinBlockWait>, SingleCursorTracker<SpinBlockWait>>;
+
+/// Creates a new spsc channel returning the only sender and receiver it will ever have
+pub fn channel<T>(size: usize) -> Result<(SpscSender<T>, SpscReceiver<T>), ChannelError> {
+    let buffer_size = checked_buffer_size(size)?;
+    let core = Arc::new(SpscRing::with_trackers(
+        buffer_size,
+        SingleProducerTracker::new(SpinBlockWait::new(0, 0)),
+        SingleCursorTracker::new(SpinBlockWait::new(0, 0)),
+    ));
+    core.reader_tracker().register(0)?;
+    let capacity = buffer_size as isize;
+    let sender = SpscSender {
+        core: core.clone(),
+        capacity,
+        cached_tail: 0,
+    };
+    let receiver = SpscReceiver {
+        core,
+        internal_cursor: -1,
+        committed_cache: -1,
+    };
+    Ok((sender, receiver))
+}
+
+// The sender and receiver are never in the same slot at once so `T` doesn't need to be `Sync`
+unsafe impl<T: Send> Send for SpscSender<T> {}
+
+#[derive(Debug)]
+pub struct SpscSender<T> {
+    core: Arc<SpscRing<T>>,
+    capacity: isize,
+    cached_tail: isize,
+}
+
+impl<T> SpscSender<T> {
+    /// The sequence the next message sent will have. There's only one sender so it's exact
+    pub fn next_sequence(&self) -> Sequence {
+        Sequence::from_cursor(self.core.sender_tracker().next_claim())
+    }
+
+    /// Send a single value to the channel. This function will block if there is no space
+    /// available in the channel.
+    pub fn send(&mut self, value: T) {
+        let claimed_id = self.core.sender_tracker().make_claim();
+        let tail = claimed_id - self.capacity;
+        if tail >= 0 && self.cached_tail <= tail {
+            self.cached_tail = self.core.reader_tracker().wait_for(tail + 1);
+        }
+        debug_assert!(tail < 0 || self.cached_tail > tail);
+
+        let old_value;
+        unsafe {
+            old_value = self.core.write(claimed_id, value);
+            fence(Ordering::Release)
+        }
+        self.core.sender_tracker().publish(claimed_id);
+        drop(old_value);
+    }
+
+    /// Turn this channel into a broadcast channel which can have any number of senders. The
+    /// receiver has to be upgraded too as it tracks its position in a way that only works with
+    /// one sender. Messages the receiver hadn't read yet are moved over in order and sequence
+    /// numbers start again from 0.
+    ///
+    /// The upgrade is one way. There's no turning a broadcast channel back into an spsc one.
+    ///
+    /// ```
+    /// let (mut sender, receiver) = nexusq::spsc::channel(4).expect("couldn't create channel");
+    /// sender.send(1);
+    /// let (sender, mut receiver) = sender.into_mpsc(receiver).dissolve();
+    /// let mut second = sender.clone();
+    /// second.send(2);
+    /// assert_eq!(receiver.recv(), 1);
+    /// assert_eq!(receiver.recv(), 2);
+    /// ```
+    ///
+    /// # Panics
+    /// If `receiver` belongs to a different channel
+    pub fn into_mpsc(self, receiver: SpscReceiver<T>) -> ChannelHandles<T> {
+        assert!(
+            Arc::ptr_eq(&self.core, &receiver.core),
+            "the receiver belongs to a different channel"
+        );
+        let read = receiver.internal_cursor;
+        drop(receiver);
+        let Ok(core) = Arc::try_unwrap(self.core) else {
+            unreachable!("the sender and receiver are the only users of the ring");
+        };
+        let published = core.sender_tracker().current();
+        // Once the values have been moved out the ring mustn't drop them again. Forgetting them
+        // first means a panic part way through leaks rather than double drops
+        core.sender_tracker().reset();
+        let capacity = core.capacity();
+        let mut handles =
+            super::channel(capacity).expect("the spsc channel already has a valid size");
+        for id in (published - capacity as isize + 1).max(0)..=published {
+            // Every id from here to published is still in its slot
+            let value = unsafe { core::ptr::read(core.slot(id)) };
+            if id > read {
+                // A new receiver makes room for the whole capacity so this never blocks
+                handles.sender.send(value);
+            }
+        }
+        handles
+    }
+}
+
+unsafe impl<T: Send> Send for SpscReceiver<T> {}
+
+#[derive(Debug)]
+pub struct SpscReceiver<T> {
+    core: Arc<SpscRing<T>>,
+    internal_cursor: isize,
+    committed_cache: isize,
+}
+
+impl<T> Drop for SpscReceiver<T> {
+    fn drop(&mut self) {
+        self.core
+            .reader_tracker()
+            .de_register(self.internal_cursor.clamp(0, isize::MAX));
+    }
+}
+
+impl<T> SpscReceiver<T>
+where
+    T: Clone,
+{
+    /// Read the next value from the channel. This function will block and wait for data to
+    /// become available.
+    pub fn recv(&mut self) -> T {
+        self.internal_cursor += 1;
+        if self.committed_cache < self.internal_cursor {
+            self.committed_cache = self.core.sender_tracker().wait_for(self.internal_cursor);
+        }
+        if self.internal_cursor > 0 {
+            self.core
+                .reader_tracker()
+                .update(self.internal_cursor - 1, self.internal_cursor);
+        }
+        debug_assert!(self.committed_cache >= self.internal_cursor);
+        fence(Ordering::Acquire);
+        unsafe { self.core.read(self.internal_cursor) }
+    }
+}
+
+#[cfg(test)]
+mod spsc_tests {
+    use super::*;
+    use crate::ReceiverError;
+
+    #[test]
+    fn send_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        for i in 0..20 {
+            sender.send(String::from("hello") + &i.to_string());
+            assert_eq!(receiver.recv(), String::from("hello") + &i.to_string());
+        }
+    }
+
+    #[test]
+    fn next_sequence() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        for i in 0..10 {
+            assert_eq!(sender.next_sequence(), Sequence::from(i));
+            sender.send(i);
+            assert_eq!(receiver.recv(), i);
+        }
+    }
+
+    #[test]
+    fn wrapping() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        // The receiver holds on to the last slot it read so only 3 fit between reads
+        for round in 0..5 {
+            for i in 0..3 {
+                sender.send(round * 3 + i);
+            }
+            for i in 0..3 {
+                assert_eq!(receiver.recv(), round * 3 + i);
+            }
+        }
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (mut sender, receiver) = channel(4).expect("couldn't create channel");
+        drop(receiver);
+        // Would block forever if the dropped receiver was still holding the tail
+        for i in 0..20 {
+            sender.send(i);
+        }
+    }
+
+    #[test]
+    fn into_mpsc() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        // Wrap around the end of the ring so the unread messages aren't all in order in memory
+        for i in 0..4 {
+            sender.send(i.to_string());
+        }
+        for i in 0..3 {
+            assert_eq!(receiver.recv(), i.to_string());
+        }
+        for i in 4..6 {
+            sender.send(i.to_string());
+        }
+        let (mut sender, mut receiver) = sender.into_mpsc(receiver).dissolve();
+        let mut second = sender.clone();
+        sender.send(String::from("first"));
+        for i in 3..6 {
+            assert_eq!(receiver.recv(), i.to_string());
+        }
+        second.send(String::from("second"));
+        assert_eq!(receiver.recv(), "first");
+        assert_eq!(receiver.recv(), "second");
+        assert!(matches!(receiver.try_recv(), Err(ReceiverError::NoNewData)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn into_mpsc_other_receiver() {
+        let (sender, _receiver) = channel::<usize>(4).expect("couldn't create channel");
+        let (_sender, receiver) = channel::<usize>(4).expect("couldn't create channel");
+        sender.into_mpsc(receiver);
+    }
+
+    #[test]
+    fn threaded() {
+        let num = 5000;
+        let (mut sender, mut receiver) = channel(8).expect("couldn't create channel");
+        let reader = std::thread::spawn(move || {
+            for i in 0..num {
+                assert_eq!(receiver.recv(), i);
+            }
+        });
+        for i in 0..num {
+            sender.send(i);
+        }
+        reader.join().expect("reader panicked");
+    }
+}
@@ -0,0 +1,222 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::tracker::Tracker;
+#[cfg(feature = "metrics")]
+use super::wait_strategy::BlockWait;
+use super::wait_strategy::{SpinBlockWait, YieldWait};
+use super::{Core, Ring};
+
+/// A window on to what's happening inside a channel. Everything is read as it is at the time of
+/// the call and can be out of date as soon as it returns.
+///
+/// Receiver positions are the slot each receiver is holding in the ring. That's the last
+/// message it read or the next one if it hasn't read anything since it attached. Comparing them
+/// with [total_sent](crate::BroadcastSender::total_sent) over time gives each receiver's rate.
+/// [Detached](crate::BroadcastReceiver::detach) receivers aren't included.
+#[derive(Debug)]
+pub struct ChannelStats<T> {
+    core: Arc<Ring<T>>,
+}
+
+impl<T> Clone for ChannelStats<T> {
+    fn clone(&self) -> Self {
+        Self {
+            core: self.core.clone(),
+        }
+    }
+}
+
+impl<T> From<Arc<Ring<T>>> for ChannelStats<T> {
+    fn from(core: Arc<Ring<T>>) -> Self {
+        Self { core }
+    }
+}
+
+impl<T> ChannelStats<T> {
+    /// The [id](crate::BroadcastReceiver::id) and position of every attached receiver
+    pub fn receiver_positions(&self) -> Vec<(usize, isize)> {
+        self.core.receivers().positions()
+    }
+
+    /// How many times senders and receivers have notified whoever is waiting on the other side.
+    /// Every send notifies every blocked receiver and receivers notify blocked senders when the
+    /// tail moves. Only available with the `metrics` feature
+    #[cfg(feature = "metrics")]
+    pub fn notify_count(&self) -> u64 {
+        self.block_waits()
+            .iter()
+            .map(|wait| wait.notify_count())
+            .sum()
+    }
+
+    /// How many times a blocked sender or receiver was woken up only to find that what it was
+    /// waiting for still wasn't there. A high number compared to [notify_count](Self::notify_count)
+    /// means notifications are waking too many waiters. Only available with the `metrics` feature
+    #[cfg(feature = "metrics")]
+    pub fn wasteful_wakeups(&self) -> u64 {
+        self.block_waits()
+            .iter()
+            .map(|wait| wait.wasteful_wakeups())
+            .sum()
+    }
+
+    /// How many times the tail of the channel, the slot the slowest receiver is holding, has had
+    /// to be searched for because the receiver holding it left or jumped ahead. Only available
+    /// with the `metrics` feature
+    #[cfg(feature = "metrics")]
+    pub fn tail_chases(&self) -> u64 {
+        self.core.reader_tracker().tail_chases()
+    }
+
+    /// How many slots have been scanned in all of the [tail_chases](Self::tail_chases). A search
+    /// scans at most the channel's capacity. A high average means there are large gaps between
+    /// receivers. Only available with the `metrics` feature
+    #[cfg(feature = "metrics")]
+    pub fn tail_chase_steps(&self) -> u64 {
+        self.core.reader_tracker().tail_chase_steps()
+    }
+
+    /// What receivers and senders block on respectively
+    #[cfg(feature = "metrics")]
+    fn block_waits(&self) -> [&BlockWait; 2] {
+        [
+            self.core.sender_tracker().wait_strategy().block_wait(),
+            self.core.reader_tracker().wait_strategy().block_wait(),
+        ]
+    }
+
+    /// What receivers wait with for something to be sent
+    pub fn receiver_wait_strategy(&self) -> &SpinBlockWait {
+        self.core.sender_tracker().wait_strategy()
+    }
+
+    /// What senders wait with for the receivers to make room
+    pub fn sender_wait_strategy(&self) -> &SpinBlockWait {
+        self.core.reader_tracker().wait_strategy()
+    }
+
+    /// What senders wait with for the senders ahead of them to publish. See
+    /// [publish_spins](crate::ChannelBuilder::publish_spins)
+    pub fn publish_wait_strategy(&self) -> &YieldWait {
+        self.core.sender_tracker().publish_wait()
+    }
+
+    /// Everything there is to know about the state of the channel on one line each. Meant for
+    /// logging when a channel seems to be stuck. It only loads what's already there so it never
+    /// blocks, but the parts are loaded one after the other and might not quite agree
+    pub fn diagnose(&self) -> String {
+        let sender_tracker = self.core.sender_tracker();
+        let tail = self.core.reader_tracker().current();
+        let mut positions = self.receiver_positions();
+        positions.sort_unstable();
+        let mut report = String::new();
+        // Writing to a string can't fail
+        let _ = writeln!(report, "capacity: {}", self.core.capacity());
+        let _ = writeln!(report, "next claim: {}", sender_tracker.next_claim());
+        let _ = writeln!(report, "published: {}", sender_tracker.current());
+        let _ = writeln!(report, "in flight: {}", sender_tracker.inflight());
+        let _ = writeln!(report, "tail: {tail}");
+        let _ = writeln!(report, "senders: {}", self.core.senders());
+        let _ = writeln!(report, "closed: {}", self.core.is_closed());
+        let _ = writeln!(report, "receivers: {}", positions.len());
+        for (id, position) in positions {
+            let _ = writeln!(report, "  receiver {id} at {position}");
+        }
+        let _ = writeln!(report, "receiver wait: {:?}", self.receiver_wait_strategy());
+        let _ = writeln!(report, "sender wait: {:?}", self.sender_wait_strategy());
+        let _ = write!(report, "publish wait: {:?}", self.publish_wait_strategy());
+        report
+    }
+
+    /// The [id](crate::BroadcastReceiver::id) and position of the receiver that's furthest
+    /// behind or None if there aren't any attached. This is the receiver holding up the senders
+    pub fn slowest_receiver_id(&self) -> Option<(usize, isize)> {
+        self.core.receivers().slowest()
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::*;
+
+    #[test]
+    fn diagnose() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        sender.send(1);
+        sender.send(2);
+        receiver.recv();
+        let report = sender.stats().diagnose();
+        for line in [
+            "capacity: 4",
+            "next claim: 2",
+            "published: 1",
+            "senders: 1",
+            "receivers: 1",
+        ] {
+            assert!(
+                report.lines().any(|l| l == line),
+                "{line} missing from {report}"
+            );
+        }
+        assert!(report.contains(&format!("receiver {} at 0", receiver.id())));
+    }
+
+    #[test]
+    fn wait_strategies() {
+        let (sender, _) = ChannelBuilder::new(8)
+            .publish_spins(7)
+            .build::<usize>()
+            .expect("couldn't create channel")
+            .dissolve();
+        let stats = sender.stats();
+        assert_eq!(stats.publish_wait_strategy().num_spins(), 7);
+        assert_eq!(stats.receiver_wait_strategy().num_spin(), 0);
+        assert_eq!(stats.sender_wait_strategy().num_yield(), 0);
+    }
+
+    #[test]
+    fn slowest_receiver() {
+        let (mut sender, mut fast) = channel(8).expect("couldn't create channel").dissolve();
+        let stats = sender.stats();
+        let mut slow = fast.clone();
+        let mut detached = fast.clone();
+        detached.detach();
+        for i in 0..5 {
+            sender.send(i);
+        }
+        for _ in 0..5 {
+            fast.recv();
+        }
+        slow.recv();
+        slow.recv();
+        assert_eq!(stats.slowest_receiver_id(), Some((slow.id(), 1)));
+        let mut positions = stats.receiver_positions();
+        positions.sort();
+        assert_eq!(positions, vec![(fast.id(), 4), (slow.id(), 1)]);
+        drop(slow);
+        assert_eq!(fast.stats().slowest_receiver_id(), Some((fast.id(), 4)));
+        drop(fast);
+        assert_eq!(stats.slowest_receiver_id(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn wakeups() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel").dissolve();
+        let stats = sender.stats();
+        let before = stats.notify_count();
+        sender.send(0);
+        assert_eq!(stats.notify_count(), before + 1);
+        assert_eq!(receiver.recv(), 0);
+        let reader = std::thread::spawn(move || receiver.recv());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        sender.send(1);
+        assert_eq!(reader.join().expect("reader panicked"), 1);
+        // The reader was only woken once there was something for it
+        assert_eq!(stats.wasteful_wakeups(), 0);
+        assert!(stats.notify_count() >= before + 2);
+    }
+}
@@ -0,0 +1,207 @@
+//! A multi producer multi consumer work queue. Every message goes to exactly one receiver
+//! instead of being broadcast to all of them.
+//!
+//! Receivers share a claim counter. Each read claims the next message, waits for it to be
+//! published and then takes it out of the ring so `T` doesn't need to be `Clone`. Receivers
+//! finish taking their messages in any order. The tracker that counts what's been taken is the
+//! same one the senders use for [relaxed publishing](crate::ChannelBuilder::relaxed_publish), so
+//! the senders get a slot back once everything before it has been taken too.
+//!
+//! Senders block once the channel is full if there are no receivers left to empty it.
+
+use alloc::sync::Arc;
+use std::sync::atomic::{fence, Ordering};
+
+use super::tracker::{ProducerTracker, SequentialProducerTracker, Tracker};
+use super::wait_strategy::SpinBlockWait;
+use super::{checked_buffer_size, ChannelError, Ring};
+
+type StealRing<T> = Ring<
+    Option<T>,
+    SequentialProducerTracker<SpinBlockWait>,
+    SequentialProducerTracker<SpinBlockWait>,
+>;
+
+/// Creates a new work stealing channel returning a sender and a receiver. Both can be cloned
+pub fn channel<T>(size: usize) -> Result<(StealSender<T>, StealReceiver<T>), ChannelError> {
+    let buffer_size = checked_buffer_size(size)?;
+    let core = Arc::new(StealRing::with_trackers(
+        buffer_size,
+        SequentialProducerTracker::new(SpinBlockWait::new(0, 0)),
+        SequentialProducerTracker::relaxed(SpinBlockWait::new(0, 0), buffer_size),
+    ));
+    let sender = StealSender {
+        core: core.clone(),
+        capacity: buffer_size as isize,
+        cached_taken: -1,
+    };
+    Ok((sender, StealReceiver { core }))
+}
+
+// Values are only ever moved to a single receiver, never shared, so `T` doesn't need to be `Sync`
+unsafe impl<T: Send> Send for StealSender<T> {}
+
+#[derive(Debug)]
+pub struct StealSender<T> {
+    core: Arc<StealRing<T>>,
+    capacity: isize,
+    // Everything up to here has been taken by a receiver
+    cached_taken: isize,
+}
+
+impl<T> Clone for StealSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            core: self.core.clone(),
+            capacity: self.capacity,
+            cached_taken: self.cached_taken,
+        }
+    }
+}
+
+impl<T> StealSender<T> {
+    /// Send a single value to the channel. This function will block if there is no space
+    /// available in the channel.
+    pub fn send(&mut self, value: T) {
+        let claimed_id = self.core.sender_tracker.make_claim();
+        // The slot is free once the value that was in it has been taken
+        let previous = claimed_id - self.capacity;
+        if self.cached_taken < previous {
+            self.cached_taken = self.core.reader_tracker.wait_for(previous);
+        }
+        let old_value;
+        unsafe {
+            old_value = self.core.write(claimed_id, Some(value));
+            fence(Ordering::Release)
+        }
+        self.core.sender_tracker.publish(claimed_id);
+        // The receiver that had the last value in the slot took it
+        debug_assert!(old_value.flatten().is_none());
+    }
+}
+
+unsafe impl<T: Send> Send for StealReceiver<T> {}
+
+#[derive(Debug)]
+pub struct StealReceiver<T> {
+    core: Arc<StealRing<T>>,
+}
+
+impl<T> Clone for StealReceiver<T> {
+    /// Creates another receiver sharing the same messages
+    fn clone(&self) -> Self {
+        Self {
+            core: self.core.clone(),
+        }
+    }
+}
+
+impl<T> StealReceiver<T> {
+    /// Take the next message nobody else has, waiting for it to be sent if needed
+    pub fn recv(&mut self) -> T {
+        let id = self.core.reader_tracker.make_claim();
+        self.core.sender_tracker.wait_for(id);
+        self.take(id)
+    }
+
+    /// Take the next message nobody else has if it's already been sent. Returns None without
+    /// waiting otherwise
+    pub fn try_recv(&mut self) -> Option<T> {
+        let published = self.core.sender_tracker.current();
+        let id = self.core.reader_tracker.try_claim(published)?;
+        Some(self.take(id))
+    }
+
+    fn take(&mut self, id: isize) -> T {
+        fence(Ordering::Acquire);
+        // Only the receiver that claimed the id looks at the slot and the senders can't have it
+        // back until the value has been taken
+        let value = unsafe { self.core.slot_mut(id) }
+            .take()
+            .expect("published slot was empty");
+        self.core.reader_tracker.publish(id);
+        value
+    }
+}
+
+#[cfg(test)]
+mod steal_tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    // Deliberately not Clone
+    #[derive(Debug, PartialEq)]
+    struct Moved(usize);
+
+    #[test]
+    fn send_recv() {
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        let mut other = receiver.clone();
+        assert_eq!(receiver.try_recv(), None);
+        for round in 0..10 {
+            for i in 0..4 {
+                sender.send(Moved(round * 4 + i));
+            }
+            // Whoever asks first gets the next one
+            assert_eq!(receiver.recv(), Moved(round * 4));
+            assert_eq!(other.recv(), Moved(round * 4 + 1));
+            assert_eq!(other.try_recv(), Some(Moved(round * 4 + 2)));
+            assert_eq!(receiver.try_recv(), Some(Moved(round * 4 + 3)));
+            assert_eq!(receiver.try_recv(), None);
+        }
+    }
+
+    #[test]
+    fn shared_between_workers() {
+        let num = 3000;
+        let (sender, receiver) = channel(16).expect("couldn't create channel");
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let mut sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num {
+                        sender.send(writer * num + i);
+                    }
+                })
+            })
+            .collect();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let mut receiver = receiver.clone();
+                // Between them the workers take everything
+                std::thread::spawn(move || {
+                    (0..2 * num / 3)
+                        .map(|_| receiver.recv())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        let mut taken: Vec<_> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker panicked"))
+            .collect();
+        taken.sort_unstable();
+        // Every message was taken exactly once
+        assert_eq!(taken, (0..2 * num).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn values_are_moved_out() {
+        let value = Arc::new(());
+        let (mut sender, mut receiver) = channel(4).expect("couldn't create channel");
+        for _ in 0..3 {
+            sender.send(value.clone());
+        }
+        let received = receiver.recv();
+        assert_eq!(Arc::strong_count(&value), 4);
+        drop(received);
+        assert_eq!(Arc::strong_count(&value), 3);
+        // The ones that weren't taken are dropped with the channel
+        drop(sender);
+        drop(receiver);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
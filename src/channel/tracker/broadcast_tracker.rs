@@ -1,11 +1,19 @@
 use alloc::vec::Vec;
+#[cfg(feature = "metrics")]
+use core::sync::atomic::AtomicU64;
 use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
 use std::sync::atomic::compiler_fence;
+use std::sync::{Mutex, PoisonError};
 
 use super::{ReceiverTracker, Tracker, TrackerError};
+use crate::channel::wait_strategy::Waitable;
 use crate::channel::WaitStrategy;
 use crate::utils::FastMod;
 
+/// Keeps a count of the readers at each position, one counter per slot. Positions a lap apart
+/// share a counter so every reader has to be less than a lap ahead of the tail. Senders can't
+/// publish further than that ahead of the tail and registering further ahead fails with
+/// [TrackerError::PositionAliased]
 #[derive(Debug)]
 pub struct MultiCursorTracker<WS> {
     // Access will always be write so no need for a more complex read write lock here.
@@ -15,6 +23,17 @@ pub struct MultiCursorTracker<WS> {
     tail: AtomicIsize,
     wait_strategy: WS,
     num_readers: AtomicIsize,
+    // Held while registering or de-registering so that readers joining and leaving can't race
+    // each other over who owns the tail
+    registration: Mutex<()>,
+    // The furthest tail any producer has assumed while there were no readers to wait for.
+    // A reader joining an empty tracker can't start before this
+    unguarded: AtomicIsize,
+    // How many times the tail has been looked for and how many slots were scanned finding it
+    #[cfg(feature = "metrics")]
+    tail_chases: AtomicU64,
+    #[cfg(feature = "metrics")]
+    tail_chase_steps: AtomicU64,
 }
 
 impl<WS> MultiCursorTracker<WS>
@@ -32,16 +51,81 @@ where
             tail: Default::default(),
             wait_strategy,
             num_readers: Default::default(),
+            registration: Default::default(),
+            unguarded: Default::default(),
+            #[cfg(feature = "metrics")]
+            tail_chases: Default::default(),
+            #[cfg(feature = "metrics")]
+            tail_chase_steps: Default::default(),
         })
     }
 
+    pub(crate) fn wait_strategy(&self) -> &WS {
+        &self.wait_strategy
+    }
+
+    /// Go back to how the tracker was when it was created. There can't be any readers
+    pub(crate) fn reset(&self) {
+        debug_assert_eq!(self.num_readers.load(Ordering::Acquire), 0);
+        for counter in &self.counters {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.unguarded.store(0, Ordering::Relaxed);
+        self.tail.store(0, Ordering::Release);
+    }
+
+    /// Register the first reader on a tracker that has none. The tail is stale at this point
+    /// as nothing has been holding it so it's moved straight to the new reader.
+    /// Producers stop ignoring the tail as soon as they see the reader count go up. Anything they
+    /// claimed before that is accounted for by `unguarded`.
+    fn register_first(&self, at: isize) -> isize {
+        self.num_readers.store(1, Ordering::SeqCst);
+        let at = at.max(self.unguarded.load(Ordering::SeqCst));
+        let idx = (at as usize).pow_2_mod(self.counters.len());
+        unsafe {
+            self.counters
+                .get_unchecked(idx)
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        self.tail.store(at, Ordering::SeqCst);
+        // Producers that saw the reader before the tail was moved are waiting on it
+        self.wait_strategy.notify();
+        at
+    }
+
+    /// How many times the tail has had to be looked for after the reader holding it left or
+    /// jumped ahead
+    #[cfg(feature = "metrics")]
+    pub(crate) fn tail_chases(&self) -> u64 {
+        self.tail_chases.load(Ordering::Relaxed)
+    }
+
+    /// How many slots have been scanned looking for the tail. The most a single search can scan
+    /// is the capacity as every reader is less than a lap ahead of the tail
+    #[cfg(feature = "metrics")]
+    pub(crate) fn tail_chase_steps(&self) -> u64 {
+        self.tail_chase_steps.load(Ordering::Relaxed)
+    }
+
     fn chase_tail(&self, from: isize) {
+        let _steps = self.find_tail(from);
+        #[cfg(feature = "metrics")]
+        {
+            self.tail_chases.fetch_add(1, Ordering::Relaxed);
+            self.tail_chase_steps
+                .fetch_add(_steps as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Move the tail to the first slot from `from` that a reader is holding. Returns how many
+    /// slots were looked at
+    fn find_tail(&self, from: isize) -> usize {
         //find the next tail by iterating over the ring
         let mut current_id = from as usize;
         loop {
             if self.num_readers.load(Ordering::Acquire) == 0 {
                 // There are no readers left!
-                break;
+                return current_id - from as usize;
             }
             let index = current_id.pow_2_mod(self.counters.len());
             let cell;
@@ -53,7 +137,9 @@ where
                 if cell.load(Ordering::Acquire) != 0
                     || self.tail.load(Ordering::Acquire) > (current_id as isize)
                 {
-                    return;
+                    // The tail has moved. Producers waiting on it need to know
+                    self.wait_strategy.notify();
+                    return current_id - from as usize + 1;
                 }
                 debug_assert!(self.tail.load(Ordering::Acquire) == current_id as isize);
             }
@@ -64,13 +150,25 @@ where
 
 impl<WS> ReceiverTracker for MultiCursorTracker<WS>
 where
-    WS: WaitStrategy,
+    WS: WaitStrategy + Sync,
 {
     fn register(&self, mut at: isize) -> Result<isize, TrackerError> {
         at = at.clamp(0, isize::MAX);
-        if at < self.tail.load(Ordering::Acquire) {
+        let _guard = self
+            .registration
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if self.num_readers.load(Ordering::SeqCst) == 0 {
+            return Ok(self.register_first(at));
+        }
+        let tail = self.tail.load(Ordering::Acquire);
+        if at < tail {
             return Err(TrackerError::PositionTooOld);
         }
+        if at - tail >= self.counters.len() as isize {
+            // It would be counted as a reader at the tail
+            return Err(TrackerError::PositionAliased);
+        }
         let idx = (at as usize).pow_2_mod(self.counters.len());
         unsafe {
             self.counters
@@ -96,6 +194,10 @@ where
         debug_assert!(to >= 0);
         debug_assert!(from >= 0);
         debug_assert!(from < to);
+        debug_assert!(
+            to - self.tail.load(Ordering::Acquire) < self.counters.len() as isize,
+            "moving to {to} would share a counter with the tail"
+        );
 
         let to_idx = (to as usize).pow_2_mod(self.counters.len());
         let from_idx = (from as usize).pow_2_mod(self.counters.len());
@@ -112,14 +214,23 @@ where
                 .fetch_sub(1, Ordering::SeqCst);
         }
         if previous == 1 && self.tail.load(Ordering::Acquire) == from {
-            self.tail.store(to, Ordering::Release);
-            //the tail has moved. notify anyone who was listening
-            self.wait_strategy.notify();
+            if to == from + 1 {
+                self.tail.store(to, Ordering::Release);
+                //the tail has moved. notify anyone who was listening
+                self.wait_strategy.notify();
+            } else {
+                // Other readers could be holding positions we've jumped over
+                self.chase_tail(from + 1);
+            }
         }
     }
 
     fn de_register(&self, at: isize) {
         if at >= 0 {
+            let _guard = self
+                .registration
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             let num_readers_left = self.num_readers.fetch_sub(1, Ordering::AcqRel) - 1;
             let index = (at as usize).pow_2_mod(self.counters.len());
             let previous;
@@ -130,6 +241,8 @@ where
                     .fetch_sub(1, Ordering::SeqCst);
             }
             if num_readers_left == 0 {
+                // There's nobody left to hold back the producers. Wake any that are waiting
+                self.wait_strategy.notify();
                 return;
             }
             if previous == 1 && at == self.tail.load(Ordering::Acquire) {
@@ -141,10 +254,24 @@ where
 }
 impl<WS> Tracker for MultiCursorTracker<WS>
 where
-    WS: WaitStrategy,
+    WS: WaitStrategy + Sync,
 {
     fn wait_for(&self, expected_tail: isize) -> isize {
-        self.wait_strategy.wait_for_geq(&self.tail, expected_tail)
+        loop {
+            if self.num_readers.load(Ordering::SeqCst) == 0 {
+                // There are no readers to wait for. Leave a mark so that a reader registering
+                // from here on starts clear of whatever the caller is about to overwrite
+                self.unguarded.fetch_max(expected_tail, Ordering::SeqCst);
+                if self.num_readers.load(Ordering::SeqCst) == 0 {
+                    // Don't return the tail. It's stale and the caller would cache it
+                    return expected_tail;
+                }
+            }
+            let tail = self.wait_strategy.wait_for_geq(self, expected_tail);
+            if tail != isize::MAX {
+                return tail;
+            }
+        }
     }
 
     fn current(&self) -> isize {
@@ -152,6 +279,20 @@ where
     }
 }
 
+impl<WS> Waitable for &MultiCursorTracker<WS>
+where
+    WS: Sync,
+{
+    type InnerType = isize;
+    /// The tail doesn't hold anything back when there are no readers
+    fn current_value(&self) -> Self::InnerType {
+        if self.num_readers.load(Ordering::Acquire) == 0 {
+            return isize::MAX;
+        }
+        self.tail.load(Ordering::Acquire)
+    }
+}
+
 #[cfg(test)]
 mod tracker_tests {
     use super::*;
@@ -181,6 +322,11 @@ mod tracker_tests {
         assert_eq!(tracker.counters[4].load(Ordering::Acquire), 1);
 
         assert!(tracker.register(2).is_err());
+        // Would share the tail's counter
+        assert!(matches!(
+            tracker.register(20),
+            Err(TrackerError::PositionAliased)
+        ));
 
         let shared_cursor_b = tracker.register(4).expect("couldn't register");
         assert_eq!(tracker.counters[4].load(Ordering::Acquire), 2);
@@ -201,4 +347,107 @@ mod tracker_tests {
         assert_eq!(tracker.tail.load(Ordering::Acquire), 7);
         assert_eq!(tracker.num_readers.load(Ordering::Acquire), 0);
     }
+
+    #[test]
+    #[cfg(feature = "metrics")]
+    fn tail_chase_steps() {
+        let tracker = MultiCursorTracker::new(16, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        let slow = tracker.register(2).expect("couldn't register");
+        tracker.register(6).expect("couldn't register");
+        tracker.de_register(slow);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), 6);
+        assert_eq!(tracker.tail_chases(), 1);
+        // 2 through to 6
+        assert_eq!(tracker.tail_chase_steps(), 5);
+    }
+
+    #[test]
+    fn failed_register_leaves_nothing_behind() {
+        let tracker = MultiCursorTracker::new(16, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        let reader = tracker.register(4).expect("couldn't register");
+        for _ in 0..10 {
+            assert!(tracker.register(2).is_err());
+            assert!(tracker.register(20).is_err());
+        }
+        assert_eq!(tracker.num_readers.load(Ordering::Acquire), 1);
+        let held: usize = tracker
+            .counters
+            .iter()
+            .map(|counter| counter.load(Ordering::Acquire))
+            .sum();
+        assert_eq!(held, 1);
+        tracker.de_register(reader);
+        assert_eq!(tracker.num_readers.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn update_past_another_reader() {
+        let tracker = MultiCursorTracker::new(16, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        let slow = tracker.register(2).expect("couldn't register");
+        let fast = tracker.register(4).expect("couldn't register");
+        tracker.update(slow, 8);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), fast);
+        tracker.update(fast, 5);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), 5);
+        tracker.update(5, 10);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), 8);
+    }
+
+    #[test]
+    fn no_readers_doesnt_block() {
+        let tracker = MultiCursorTracker::new(16, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        let cursor = tracker.register(0).expect("couldn't register");
+        tracker.de_register(cursor);
+        assert_eq!(tracker.wait_for(20), 20);
+
+        assert_eq!(tracker.register(30).expect("couldn't register"), 30);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), 30);
+        assert_eq!(tracker.wait_for(25), 30);
+    }
+
+    #[test]
+    fn first_reader_starts_after_unguarded_producers() {
+        let tracker = MultiCursorTracker::new(16, BusyWait::default())
+            .expect("couldn't create multi cursor tracker");
+        // A producer ran ahead while nobody was reading
+        assert_eq!(tracker.wait_for(40), 40);
+        // A reader that thinks the latest is 35 would have its slot overwritten
+        let at = tracker.register(35).expect("couldn't register");
+        assert_eq!(at, 40);
+        assert_eq!(tracker.tail.load(Ordering::Acquire), 40);
+        assert_eq!(tracker.counters[40 % 16].load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn concurrent_first_readers() {
+        use std::sync::Arc;
+        for _ in 0..100 {
+            let tracker = Arc::new(
+                MultiCursorTracker::new(16, BusyWait::default())
+                    .expect("couldn't create multi cursor tracker"),
+            );
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let tracker = tracker.clone();
+                    std::thread::spawn(move || tracker.register(3 + i))
+                })
+                .collect();
+            let positions: Vec<isize> = handles
+                .into_iter()
+                .filter_map(|h| h.join().expect("register panicked").ok())
+                .collect();
+            assert!(!positions.is_empty());
+            // The tail must never be ahead of a live reader
+            let slowest = *positions.iter().min().unwrap();
+            assert!(tracker.tail.load(Ordering::Acquire) <= slowest);
+            assert_eq!(
+                tracker.num_readers.load(Ordering::Acquire),
+                positions.len() as isize
+            );
+        }
+    }
 }
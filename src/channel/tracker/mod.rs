@@ -1,10 +1,14 @@
 mod broadcast_tracker;
 mod sequential_producer_tracker;
+mod single_cursor_tracker;
+mod single_producer_tracker;
 
 use thiserror::Error as ThisError;
 
 pub use broadcast_tracker::MultiCursorTracker;
 pub use sequential_producer_tracker::SequentialProducerTracker;
+pub use single_cursor_tracker::SingleCursorTracker;
+pub use single_producer_tracker::SingleProducerTracker;
 
 #[derive(ThisError, Debug)]
 pub enum TrackerError {
@@ -12,6 +16,8 @@ pub enum TrackerError {
     InvalidSize,
     #[error("the requested position no longer exists")]
     PositionTooOld,
+    #[error("the requested position is a lap or more ahead of the slowest reader")]
+    PositionAliased,
 }
 
 pub trait Tracker {
@@ -27,5 +33,9 @@ pub trait ReceiverTracker: Tracker {
 
 pub trait ProducerTracker: Tracker {
     fn make_claim(&self) -> isize;
+    /// Claim `n` sequential ids returning the first
+    fn make_claims(&self, n: usize) -> isize;
     fn publish(&self, id: isize);
+    /// Publish every id from `from` to `to` inclusive in one go
+    fn publish_range(&self, from: isize, to: isize);
 }
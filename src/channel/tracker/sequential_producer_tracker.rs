@@ -1,14 +1,26 @@
+use alloc::boxed::Box;
 use core::sync::atomic::{AtomicIsize, Ordering};
 
 use super::{ProducerTracker, Tracker};
+use crate::channel::wait_strategy::YieldWait;
 use crate::channel::WaitStrategy;
+use crate::utils::{validate_ordering, CachePadded};
 
 #[derive(Debug)]
 pub struct SequentialProducerTracker<WS> {
-    claimed: AtomicIsize,
+    // Producers claim and consumers read published so they're kept on separate cache lines
+    claimed: CachePadded<AtomicIsize>,
     committed: AtomicIsize,
-    published: AtomicIsize,
+    published: CachePadded<AtomicIsize>,
     wait_strategy: WS,
+    // Used while waiting for the producers ahead of us to publish. Spins for a while then yields
+    // so a producer that's been descheduled mid publish can get back on to the core
+    publish_wait: YieldWait,
+    // Set for relaxed publishing. Each slot holds the last id published into it. Producers mark
+    // their slots here and whoever fills a gap moves published forward, so nobody waits
+    ready: Option<Box<[AtomicIsize]>>,
+    // Consumers are only notified when a multiple of this is published
+    notify_every: isize,
 }
 
 impl<WS> SequentialProducerTracker<WS>
@@ -16,11 +28,150 @@ where
     WS: WaitStrategy,
 {
     pub fn new(wait_strategy: WS) -> Self {
+        Self::with_publish_wait(wait_strategy, Default::default())
+    }
+
+    pub(crate) fn wait_strategy(&self) -> &WS {
+        &self.wait_strategy
+    }
+
+    /// What a producer waits with for the ones ahead of it to publish
+    pub(crate) fn publish_wait(&self) -> &YieldWait {
+        &self.publish_wait
+    }
+
+    /// Wait until everything up to and including `id` has been published. Producers wait for
+    /// each other with this rather than the consumers' wait strategy as publishing doesn't
+    /// always notify
+    pub(crate) fn wait_published(&self, id: isize) -> isize {
+        self.publish_wait.wait_for_geq(&*self.published, id)
+    }
+
+    /// The id the next claim will get
+    pub(crate) fn next_claim(&self) -> isize {
+        self.claimed.load(Ordering::Acquire)
+    }
+
+    /// Go back to nothing having been claimed or published
+    pub(crate) fn reset(&self) {
+        self.claimed.store(0, Ordering::Relaxed);
+        if let Some(ready) = &self.ready {
+            for slot in ready.iter() {
+                slot.store(-1, Ordering::Relaxed);
+            }
+        }
+        self.published.store(-1, Ordering::Release);
+    }
+
+    /// Claim the next id only if it's no later than `limit`
+    pub(crate) fn try_claim(&self, limit: isize) -> Option<isize> {
+        let mut claimed = self.claimed.load(Ordering::Acquire);
+        loop {
+            if claimed > limit {
+                return None;
+            }
+            match self.claimed.compare_exchange_weak(
+                claimed,
+                claimed + 1,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(claimed),
+                Err(current) => claimed = current,
+            }
+        }
+    }
+
+    /// How many ids have been claimed but not published yet
+    pub(crate) fn inflight(&self) -> usize {
+        // Published can't pass claimed so loading it first means this can't go negative
+        let published = self.published.load(Ordering::Acquire);
+        let claimed = self.claimed.load(Ordering::Acquire);
+        (claimed - (published + 1)).max(0) as usize
+    }
+
+    pub fn with_publish_wait(wait_strategy: WS, publish_wait: YieldWait) -> Self {
         Self {
             claimed: Default::default(),
             committed: AtomicIsize::new(-1),
-            published: AtomicIsize::new(-1),
+            published: CachePadded(AtomicIsize::new(-1)),
             wait_strategy,
+            publish_wait,
+            ready: None,
+            notify_every: 1,
+        }
+    }
+
+    /// Only notify consumers when publishing moves past a multiple of `granularity` rather than
+    /// on every publish. Consumers that are blocked wake up once for every `granularity`
+    /// messages. Anything published since the last notification is only seen by a blocked
+    /// consumer after a [flush](Self::flush). Ignored with [relaxed](Self::relaxed) publishing
+    /// where a producer can't tell whether it's the one that published past the multiple
+    pub fn with_notify_granularity(mut self, granularity: usize) -> Self {
+        if self.ready.is_none() {
+            self.notify_every = granularity.clamp(1, isize::MAX as usize) as isize;
+        }
+        self
+    }
+
+    /// Wake up consumers blocked on messages that were published without notifying them
+    pub(crate) fn flush(&self) {
+        if self.notify_every > 1 {
+            self.wait_strategy.notify();
+        }
+    }
+
+    /// Producers don't wait for the ones ahead of them to publish. A producer marks its slots as
+    /// ready and moves published over every ready slot that follows it, including any that
+    /// producers after it marked while it was writing. Receivers still see messages in the order
+    /// they were claimed but nothing after a slow producer's message until it publishes.
+    /// `buffer_size` must be the ring's size
+    pub fn relaxed(wait_strategy: WS, buffer_size: usize) -> Self {
+        debug_assert!(buffer_size.is_power_of_two());
+        Self {
+            ready: Some((0..buffer_size).map(|_| AtomicIsize::new(-1)).collect()),
+            ..Self::new(wait_strategy)
+        }
+    }
+
+    /// [publish](ProducerTracker::publish) calling `on_stall` every time it checks whether the
+    /// producer ahead of it has published yet, instead of spinning. Never calls it with
+    /// [relaxed](Self::relaxed) publishing as there's nothing to wait for
+    pub(crate) fn publish_with(&self, id: isize, mut on_stall: impl FnMut()) {
+        validate_ordering!(
+            id < self.claimed.load(Ordering::Acquire),
+            "publishing {id} which hasn't been claimed"
+        );
+        if self.ready.is_none() {
+            while self.published.load(Ordering::Acquire) != id - 1 {
+                on_stall();
+            }
+        }
+        self.publish(id);
+    }
+
+    /// Move published over every ready slot after it
+    fn advance(&self, ready: &[AtomicIsize]) {
+        let mask = ready.len() - 1;
+        let mut published = self.published.load(Ordering::SeqCst);
+        loop {
+            let mut to = published;
+            // Slots from the last lap hold an older id
+            while ready[(to + 1) as usize & mask].load(Ordering::SeqCst) == to + 1 {
+                to += 1;
+            }
+            if to == published {
+                return;
+            }
+            // Losing means another producer moved it. Carry on from where they got to in case
+            // a slot was marked after they looked
+            match self
+                .published
+                .compare_exchange(published, to, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => published = to,
+                Err(current) => published = current,
+            }
         }
     }
 }
@@ -30,7 +181,7 @@ where
     WS: WaitStrategy,
 {
     fn wait_for(&self, expected: isize) -> isize {
-        self.wait_strategy.wait_for_geq(&self.published, expected)
+        self.wait_strategy.wait_for_geq(&*self.published, expected)
     }
 
     fn current(&self) -> isize {
@@ -46,14 +197,167 @@ where
         self.claimed.fetch_add(1, Ordering::SeqCst)
     }
 
+    fn make_claims(&self, n: usize) -> isize {
+        self.claimed.fetch_add(n as isize, Ordering::SeqCst)
+    }
+
     fn publish(&self, id: isize) {
+        self.publish_range(id, id)
+    }
+
+    fn publish_range(&self, from: isize, to: isize) {
+        debug_assert!(from <= to);
         // We don't need the compare and the swap to be a single atomic instruction.
         // It's cheaper to just do loads and then store when it is ready.
         // The algorithm will guarantee this is okay
-        while self.published.load(Ordering::Relaxed) != id - 1 {
-            core::hint::spin_loop();
+        validate_ordering!(
+            to < self.claimed.load(Ordering::Acquire),
+            "publishing {to} which hasn't been claimed"
+        );
+        if let Some(ready) = &self.ready {
+            let mask = ready.len() - 1;
+            // Marking and then looking at published pairs with advance marking and then looking
+            // at the next slot. One of the two always sees the other
+            for id in from..=to {
+                ready[id as usize & mask].store(id, Ordering::SeqCst);
+            }
+            self.advance(ready);
+        } else {
+            self.publish_wait.wait_for_eq(&*self.published, from - 1);
+            self.published.store(to, Ordering::Release);
+        }
+        if self.notify_every == 1 || (to + 1) / self.notify_every > from / self.notify_every {
+            self.wait_strategy.notify();
+        }
+    }
+}
+
+#[cfg(test)]
+mod sequential_producer_tracker_tests {
+    use super::*;
+    use crate::channel::wait_strategy::BusyWait;
+    use std::sync::Arc;
+
+    #[test]
+    #[cfg(all(feature = "validate-ordering", debug_assertions))]
+    #[should_panic(expected = "hasn't been claimed")]
+    fn publish_unclaimed() {
+        let tracker = SequentialProducerTracker::new(BusyWait::default());
+        tracker.publish(0);
+    }
+
+    #[test]
+    fn inflight() {
+        let tracker = SequentialProducerTracker::new(BusyWait::default());
+        assert_eq!(tracker.inflight(), 0);
+        let first = tracker.make_claims(3);
+        assert_eq!(tracker.inflight(), 3);
+        tracker.publish_range(first, first + 1);
+        assert_eq!(tracker.inflight(), 1);
+        tracker.publish(first + 2);
+        assert_eq!(tracker.inflight(), 0);
+    }
+
+    #[test]
+    fn publish_waits_for_predecessor() {
+        let tracker = Arc::new(SequentialProducerTracker::with_publish_wait(
+            BusyWait::default(),
+            YieldWait::new(0),
+        ));
+        let first = tracker.make_claim();
+        let second = tracker.make_claim();
+        let publisher = {
+            let tracker = tracker.clone();
+            std::thread::spawn(move || tracker.publish(second))
+        };
+        // The second claim can't be visible before the first
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(tracker.current(), -1);
+        tracker.publish(first);
+        publisher.join().expect("publisher panicked");
+        assert_eq!(tracker.current(), second);
+    }
+
+    #[test]
+    fn publish_with() {
+        let tracker = Arc::new(SequentialProducerTracker::new(BusyWait::default()));
+        let first = tracker.make_claim();
+        let second = tracker.make_claim();
+        let publisher = {
+            let tracker = tracker.clone();
+            std::thread::spawn(move || {
+                let mut stalls = 0;
+                tracker.publish_with(second, || {
+                    stalls += 1;
+                    std::thread::yield_now();
+                });
+                stalls
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        tracker.publish(first);
+        assert!(publisher.join().expect("publisher panicked") > 0);
+        assert_eq!(tracker.current(), second);
+    }
+
+    #[test]
+    fn notify_granularity() {
+        #[derive(Default)]
+        struct CountNotifies(core::sync::atomic::AtomicUsize);
+        impl WaitStrategy for CountNotifies {
+            fn wait<V: crate::channel::wait_strategy::Waitable>(
+                &self,
+                value: V,
+                expected: V::InnerType,
+                check: fn(&V, &V::InnerType) -> Option<V::InnerType>,
+            ) -> V::InnerType {
+                BusyWait::default().wait(value, expected, check)
+            }
+            fn notify(&self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
         }
-        self.published.store(id, Ordering::Release);
-        self.wait_strategy.notify();
+
+        let tracker =
+            SequentialProducerTracker::new(CountNotifies::default()).with_notify_granularity(4);
+        let notified = || tracker.wait_strategy().0.load(Ordering::Relaxed);
+        let first = tracker.make_claims(3);
+        tracker.publish_range(first, first + 2);
+        assert_eq!(notified(), 0);
+        tracker.publish(tracker.make_claim());
+        assert_eq!(notified(), 1);
+        // A range that passes a multiple notifies once
+        let next = tracker.make_claims(6);
+        tracker.publish_range(next, next + 5);
+        assert_eq!(notified(), 2);
+        tracker.flush();
+        assert_eq!(notified(), 3);
+    }
+
+    #[test]
+    fn try_claim() {
+        let tracker = SequentialProducerTracker::new(BusyWait::default());
+        assert_eq!(tracker.try_claim(-1), None);
+        assert_eq!(tracker.try_claim(0), Some(0));
+        assert_eq!(tracker.try_claim(0), None);
+        assert_eq!(tracker.make_claim(), 1);
+    }
+
+    #[test]
+    fn relaxed_publish() {
+        let tracker = SequentialProducerTracker::relaxed(BusyWait::default(), 4);
+        let first = tracker.make_claim();
+        let rest = tracker.make_claims(2);
+        // Doesn't wait for the first but can't be seen before it either
+        tracker.publish_range(rest, rest + 1);
+        assert_eq!(tracker.current(), -1);
+        tracker.publish(first);
+        assert_eq!(tracker.current(), rest + 1);
+        // Slots from the last lap aren't mistaken for ready ones
+        let next = tracker.make_claims(4);
+        tracker.publish_range(next + 1, next + 3);
+        assert_eq!(tracker.current(), rest + 1);
+        tracker.publish(next);
+        assert_eq!(tracker.current(), next + 3);
     }
 }
@@ -0,0 +1,82 @@
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+use super::{ReceiverTracker, Tracker, TrackerError};
+use crate::channel::WaitStrategy;
+
+/// Receiver tracker for a channel that can only ever have one receiver.
+/// The tail is just the position of that receiver so moving it is a release store.
+/// Once the receiver is de-registered the tail is parked at `isize::MAX` so the producer never
+/// waits again. There's nobody left to wait for and no way for another receiver to join.
+#[derive(Debug)]
+pub struct SingleCursorTracker<WS> {
+    cursor: AtomicIsize,
+    wait_strategy: WS,
+}
+
+impl<WS> SingleCursorTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    pub fn new(wait_strategy: WS) -> Self {
+        Self {
+            cursor: Default::default(),
+            wait_strategy,
+        }
+    }
+}
+
+impl<WS> ReceiverTracker for SingleCursorTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    fn register(&self, mut at: isize) -> Result<isize, TrackerError> {
+        at = at.clamp(0, isize::MAX);
+        if at < self.cursor.load(Ordering::Acquire) {
+            return Err(TrackerError::PositionTooOld);
+        }
+        self.cursor.store(at, Ordering::Release);
+        Ok(at)
+    }
+
+    fn update(&self, from: isize, to: isize) {
+        debug_assert!(from < to);
+        debug_assert_eq!(self.cursor.load(Ordering::Relaxed), from);
+        self.cursor.store(to, Ordering::Release);
+        self.wait_strategy.notify();
+    }
+
+    fn de_register(&self, _: isize) {
+        self.cursor.store(isize::MAX, Ordering::Release);
+        self.wait_strategy.notify();
+    }
+}
+
+impl<WS> Tracker for SingleCursorTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    fn wait_for(&self, expected_tail: isize) -> isize {
+        self.wait_strategy.wait_for_geq(&self.cursor, expected_tail)
+    }
+
+    fn current(&self) -> isize {
+        self.cursor.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tracker_tests {
+    use super::*;
+    use crate::channel::wait_strategy::BusyWait;
+
+    #[test]
+    fn update_de_register() {
+        let tracker = SingleCursorTracker::new(BusyWait::default());
+        assert_eq!(tracker.register(0).expect("couldn't register"), 0);
+        tracker.update(0, 1);
+        assert_eq!(tracker.current(), 1);
+        assert!(tracker.register(0).is_err());
+        tracker.de_register(1);
+        assert_eq!(tracker.wait_for(100), isize::MAX);
+    }
+}
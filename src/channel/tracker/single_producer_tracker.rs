@@ -0,0 +1,98 @@
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+use super::{ProducerTracker, Tracker};
+use crate::channel::WaitStrategy;
+
+/// Producer tracker for a channel that can only ever have one producer.
+/// With only one writer there's nothing to race on so claims are a plain load and store and
+/// publishing is a release store. Nothing has to wait for other producers to publish first.
+#[derive(Debug)]
+pub struct SingleProducerTracker<WS> {
+    claimed: AtomicIsize,
+    published: AtomicIsize,
+    wait_strategy: WS,
+}
+
+impl<WS> SingleProducerTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    pub fn new(wait_strategy: WS) -> Self {
+        Self {
+            claimed: Default::default(),
+            published: AtomicIsize::new(-1),
+            wait_strategy,
+        }
+    }
+
+    /// The id the next claim will get
+    pub(crate) fn next_claim(&self) -> isize {
+        self.claimed.load(Ordering::Relaxed)
+    }
+
+    /// Go back to nothing having been claimed or published
+    pub(crate) fn reset(&self) {
+        self.claimed.store(0, Ordering::Relaxed);
+        self.published.store(-1, Ordering::Release);
+    }
+}
+
+impl<WS> Tracker for SingleProducerTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    fn wait_for(&self, expected: isize) -> isize {
+        self.wait_strategy.wait_for_geq(&self.published, expected)
+    }
+
+    fn current(&self) -> isize {
+        self.published.load(Ordering::Acquire)
+    }
+}
+
+impl<WS> ProducerTracker for SingleProducerTracker<WS>
+where
+    WS: WaitStrategy,
+{
+    fn make_claim(&self) -> isize {
+        // Only the one producer ever touches claimed
+        let claimed = self.claimed.load(Ordering::Relaxed);
+        self.claimed.store(claimed + 1, Ordering::Relaxed);
+        claimed
+    }
+
+    fn make_claims(&self, n: usize) -> isize {
+        let claimed = self.claimed.load(Ordering::Relaxed);
+        self.claimed.store(claimed + n as isize, Ordering::Relaxed);
+        claimed
+    }
+
+    fn publish(&self, id: isize) {
+        self.publish_range(id, id)
+    }
+
+    fn publish_range(&self, from: isize, to: isize) {
+        debug_assert!(from <= to);
+        debug_assert_eq!(self.published.load(Ordering::Relaxed), from - 1);
+        self.published.store(to, Ordering::Release);
+        self.wait_strategy.notify();
+    }
+}
+
+#[cfg(test)]
+mod tracker_tests {
+    use super::*;
+    use crate::channel::wait_strategy::BusyWait;
+
+    #[test]
+    fn claim_publish() {
+        let tracker = SingleProducerTracker::new(BusyWait::default());
+        assert_eq!(tracker.current(), -1);
+        for id in 0..10 {
+            assert_eq!(tracker.make_claim(), id);
+            tracker.publish(id);
+            assert_eq!(tracker.current(), id);
+        }
+        assert_eq!(tracker.wait_for(5), 9);
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "metrics")]
+use core::sync::atomic::AtomicU64;
 use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
 
 pub trait Waitable: Sync {
@@ -85,6 +87,11 @@ impl YieldWait {
     pub fn new(num_spins: u32) -> Self {
         Self { num_spins }
     }
+
+    /// How many times it spins before it starts yielding
+    pub fn num_spins(&self) -> u32 {
+        self.num_spins
+    }
 }
 
 impl WaitStrategy for YieldWait {
@@ -132,6 +139,21 @@ impl SleepWait {
             num_yield,
         }
     }
+
+    /// How long it sleeps for each time once it's done spinning and yielding
+    pub fn sleep_time(&self) -> std::time::Duration {
+        self.sleep_time_ns
+    }
+
+    /// How many times it spins before it starts yielding
+    pub fn num_spin(&self) -> u32 {
+        self.num_spin
+    }
+
+    /// How many times it yields before it starts sleeping
+    pub fn num_yield(&self) -> u32 {
+        self.num_yield
+    }
 }
 
 impl WaitStrategy for SleepWait {
@@ -174,11 +196,16 @@ pub struct SpinBlockWait {
     block_wait: BlockWait,
     num_spin: u32,
     num_yield: u32,
+    // Spins for this long first. See TimedSpinWait
+    spin_for: Option<std::time::Duration>,
 }
 
 impl Clone for SpinBlockWait {
     fn clone(&self) -> Self {
-        Self::new(self.num_spin, self.num_yield)
+        Self {
+            spin_for: self.spin_for,
+            ..Self::new(self.num_spin, self.num_yield)
+        }
     }
 }
 
@@ -194,8 +221,34 @@ impl SpinBlockWait {
             block_wait: Default::default(),
             num_spin,
             num_yield,
+            spin_for: None,
         }
     }
+
+    /// How many times it spins before it starts yielding
+    pub fn num_spin(&self) -> u32 {
+        self.num_spin
+    }
+
+    /// How many times it yields before it blocks
+    pub fn num_yield(&self) -> u32 {
+        self.num_yield
+    }
+
+    /// How long it spins for when it was made from a [TimedSpinWait]
+    pub fn spin_for(&self) -> Option<std::time::Duration> {
+        self.spin_for
+    }
+
+    /// Start listening for the next notify. The listener can be awaited
+    pub(crate) fn listen(&self) -> event_listener::EventListener {
+        self.block_wait.listen()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn block_wait(&self) -> &BlockWait {
+        &self.block_wait
+    }
 }
 impl WaitStrategy for SpinBlockWait {
     #[inline(always)]
@@ -205,6 +258,18 @@ impl WaitStrategy for SpinBlockWait {
         expected: V::InnerType,
         check: fn(&V, &V::InnerType) -> Option<V::InnerType>,
     ) -> V::InnerType {
+        if let Some(spin_for) = self.spin_for {
+            let start = std::time::Instant::now();
+            loop {
+                if let Some(result) = check(&value, &expected) {
+                    return result;
+                }
+                if start.elapsed() >= spin_for {
+                    break;
+                }
+                core::hint::spin_loop();
+            }
+        }
         for _ in 0..self.num_spin {
             if let Some(result) = check(&value, &expected) {
                 return result;
@@ -226,9 +291,92 @@ impl WaitStrategy for SpinBlockWait {
     }
 }
 
+/// Spins for a fixed amount of time rather than a fixed number of iterations then blocks.
+/// Useful when the spin budget comes from a latency target. Channels wait with a
+/// [SpinBlockWait] so pass this to [receiver_wait](crate::ChannelBuilder::receiver_wait) to
+/// use it
+#[derive(Debug, Clone)]
+pub struct TimedSpinWait {
+    inner: SpinBlockWait,
+}
+
+impl Default for TimedSpinWait {
+    fn default() -> Self {
+        Self::new(std::time::Duration::from_micros(10))
+    }
+}
+
+impl TimedSpinWait {
+    pub fn new(spin_for: std::time::Duration) -> Self {
+        Self {
+            inner: SpinBlockWait {
+                spin_for: Some(spin_for),
+                ..SpinBlockWait::new(0, 0)
+            },
+        }
+    }
+
+    /// How long it spins for before it blocks
+    pub fn spin_for(&self) -> std::time::Duration {
+        self.inner.spin_for.unwrap_or_default()
+    }
+}
+
+impl From<TimedSpinWait> for SpinBlockWait {
+    fn from(wait: TimedSpinWait) -> Self {
+        wait.inner
+    }
+}
+
+impl WaitStrategy for TimedSpinWait {
+    #[inline(always)]
+    fn wait<V: Waitable>(
+        &self,
+        value: V,
+        expected: V::InnerType,
+        check: fn(&V, &V::InnerType) -> Option<V::InnerType>,
+    ) -> V::InnerType {
+        self.inner.wait(value, expected, check)
+    }
+
+    #[inline(always)]
+    fn notify(&self) {
+        self.inner.notify();
+    }
+}
+
+/// Blocks on an [event_listener::Event]. Listening allocates and event-listener has no way to
+/// report that failing. Like any other allocation in Rust running out of memory there aborts
+/// the process, so there's no error to fall back to spinning on
 #[derive(Debug, Default)]
 pub struct BlockWait {
     event: event_listener::Event,
+    // Counting costs a read-modify-write on every publish so it's only done with the metrics
+    // feature
+    #[cfg(feature = "metrics")]
+    notifies: AtomicU64,
+    // Times a waiter was woken up but what it was waiting for wasn't there
+    #[cfg(feature = "metrics")]
+    wasteful_wakeups: AtomicU64,
+}
+
+impl BlockWait {
+    /// How many times waiters have been notified
+    #[cfg(feature = "metrics")]
+    pub(crate) fn notify_count(&self) -> u64 {
+        self.notifies.load(Ordering::Relaxed)
+    }
+
+    /// How many times a waiter was woken up for nothing and had to go back to waiting
+    #[cfg(feature = "metrics")]
+    pub(crate) fn wasteful_wakeups(&self) -> u64 {
+        self.wasteful_wakeups.load(Ordering::Relaxed)
+    }
+
+    /// Start listening for the next notify. The listener can be awaited
+    pub(crate) fn listen(&self) -> event_listener::EventListener {
+        self.event.listen()
+    }
 }
 
 impl Clone for BlockWait {
@@ -245,20 +393,87 @@ impl WaitStrategy for BlockWait {
         expected: V::InnerType,
         check: fn(&V, &V::InnerType) -> Option<V::InnerType>,
     ) -> V::InnerType {
+        if let Some(result) = check(&value, &expected) {
+            return result;
+        }
         loop {
+            // Listening before the second check means a notify can't be lost between the two
+            let listener = self.event.listen();
             if let Some(result) = check(&value, &expected) {
                 return result;
             }
-            let listener = self.event.listen();
+            // Every waiter on the strategy is woken by every notify, whatever it's waiting for.
+            // Waking up doesn't mean the value we want is there yet so this has to loop
+            listener.wait();
             if let Some(result) = check(&value, &expected) {
                 return result;
             }
-            listener.wait();
+            #[cfg(feature = "metrics")]
+            self.wasteful_wakeups.fetch_add(1, Ordering::Relaxed);
         }
     }
 
     #[inline(always)]
     fn notify(&self) {
+        #[cfg(feature = "metrics")]
+        self.notifies.fetch_add(1, Ordering::Relaxed);
+        // This is already cheap when nobody is listening. The event only takes its lock if
+        // there's a listener that hasn't been notified. What's left is a SeqCst fence which
+        // can't be skipped. It orders the store being notified about before checking for
+        // listeners, pairing with a waiter listening before it checks the value. Without it a
+        // waiter could miss the store and the notify both and sleep forever
         self.event.notify(usize::MAX);
     }
 }
+
+#[cfg(test)]
+mod wait_strategy_tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn wait_across_threads(wait_strategy: impl WaitStrategy + Send + Sync + 'static) {
+        let wait_strategy = Arc::new(wait_strategy);
+        let value = Arc::new(AtomicIsize::new(0));
+        let setter = {
+            let wait_strategy = wait_strategy.clone();
+            let value = value.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10));
+                value.store(3, Ordering::Release);
+                wait_strategy.notify();
+            })
+        };
+        assert_eq!(wait_strategy.wait_for_geq(&*value, 2), 3);
+        setter.join().expect("setter panicked");
+    }
+
+    #[test]
+    fn block_wait_ignores_early_notify() {
+        let wait_strategy = Arc::new(BlockWait::default());
+        let value = Arc::new(AtomicIsize::new(0));
+        let setter = {
+            let wait_strategy = wait_strategy.clone();
+            let value = value.clone();
+            std::thread::spawn(move || {
+                for i in 1..=5 {
+                    std::thread::sleep(Duration::from_millis(2));
+                    value.store(i, Ordering::Release);
+                    wait_strategy.notify();
+                }
+            })
+        };
+        assert_eq!(wait_strategy.wait_for_geq(&*value, 5), 5);
+        setter.join().expect("setter panicked");
+    }
+
+    #[test]
+    fn timed_spin_blocks_after_budget() {
+        wait_across_threads(TimedSpinWait::new(Duration::from_micros(50)));
+    }
+
+    #[test]
+    fn timed_spin_within_budget() {
+        wait_across_threads(TimedSpinWait::new(Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,214 @@
+//! A channel that only ever holds the latest value.
+//!
+//! Unlike the broadcast channel nothing is replayed. Every [WatchSender::send] replaces the
+//! value without waiting for receivers to read it, so a slow receiver just sees fewer of the
+//! values. Each receiver remembers the version it last saw so it knows when there's something
+//! new.
+//!
+//! The value is kept behind a lock rather than in a ring slot. Ring receivers read values in
+//! place and hold on to their slot so it can't be overwritten, which would make every send wait
+//! for every receiver. With the lock a send only waits while a receiver is
+//! [borrowing](WatchReceiver::borrow) the value, so keep borrows short.
+//!
+//! ```
+//! let (sender, mut receiver) = nexusq::watch::channel(0);
+//! sender.send(1);
+//! sender.send(2);
+//! assert!(receiver.has_changed());
+//! assert_eq!(receiver.recv(), 2);
+//! assert!(!receiver.has_changed());
+//! ```
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+use super::wait_strategy::{SpinBlockWait, WaitStrategy};
+
+#[derive(Debug)]
+struct Shared<T> {
+    // A send can't overwrite the value in place while a receiver is reading it so it's behind a
+    // lock rather than in a ring slot
+    value: RwLock<T>,
+    // Bumped every time the value is replaced
+    version: AtomicIsize,
+    wait_strategy: SpinBlockWait,
+}
+
+impl<T> Shared<T> {
+    fn read(&self) -> RwLockReadGuard<'_, T> {
+        // Replacing the value can't leave it half written so a panic while holding the lock
+        // doesn't matter
+        self.value.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Creates a new watch channel holding `initial`. Receivers don't see the initial value as a
+/// change
+pub fn channel<T>(initial: T) -> (WatchSender<T>, WatchReceiver<T>) {
+    let shared = Arc::new(Shared {
+        value: RwLock::new(initial),
+        version: AtomicIsize::new(0),
+        wait_strategy: SpinBlockWait::new(0, 0),
+    });
+    let receiver = WatchReceiver {
+        shared: shared.clone(),
+        seen: 0,
+    };
+    (WatchSender { shared }, receiver)
+}
+
+#[derive(Debug)]
+pub struct WatchSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for WatchSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> WatchSender<T> {
+    /// Replace the value. This doesn't wait for receivers to catch up but it does wait for any
+    /// [borrow](WatchReceiver::borrow) of the current value to be dropped
+    pub fn send(&self, value: T) {
+        let old_value = {
+            let mut current = self
+                .shared
+                .value
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let old_value = core::mem::replace(&mut *current, value);
+            // Bumped while the lock is held so a receiver that sees the new version gets the
+            // new value
+            self.shared.version.fetch_add(1, Ordering::Release);
+            old_value
+        };
+        self.shared.wait_strategy.notify();
+        drop(old_value);
+    }
+
+    /// Create a new receiver. It doesn't see the current value as a change
+    pub fn subscribe(&self) -> WatchReceiver<T> {
+        WatchReceiver {
+            shared: self.shared.clone(),
+            seen: self.shared.version.load(Ordering::Acquire),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WatchReceiver<T> {
+    shared: Arc<Shared<T>>,
+    seen: isize,
+}
+
+impl<T> Clone for WatchReceiver<T> {
+    /// The clone has seen the same version as this receiver
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> WatchReceiver<T> {
+    /// Has the value been replaced since this receiver last marked it as seen
+    pub fn has_changed(&self) -> bool {
+        self.shared.version.load(Ordering::Acquire) != self.seen
+    }
+
+    /// Look at the latest value without marking it as seen. Senders block while it's
+    /// borrowed so don't hold on to it
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.shared.read()
+    }
+
+    /// Look at the latest value and mark it as seen
+    pub fn borrow_and_update(&mut self) -> RwLockReadGuard<'_, T> {
+        let value = self.shared.read();
+        // Nothing can be sent while the lock is held so this is the version being borrowed
+        self.seen = self.shared.version.load(Ordering::Acquire);
+        value
+    }
+
+    /// Wait for the value to be replaced. Returns straight away if it's already changed
+    pub fn changed(&self) {
+        self.shared
+            .wait_strategy
+            .wait_for_geq(&self.shared.version, self.seen + 1);
+    }
+}
+
+impl<T> WatchReceiver<T>
+where
+    T: Clone,
+{
+    /// Wait for the value to change and return a copy of the latest one
+    pub fn recv(&mut self) -> T {
+        self.changed();
+        self.borrow_and_update().clone()
+    }
+
+    /// Return a copy of the latest value if it has changed without waiting
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.has_changed().then(|| self.borrow_and_update().clone())
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+
+    #[test]
+    fn latest_only() {
+        let (sender, mut receiver) = channel(String::from("initial"));
+        assert!(!receiver.has_changed());
+        assert_eq!(*receiver.borrow(), "initial");
+        assert_eq!(receiver.try_recv(), None);
+        for i in 0..10 {
+            sender.send(i.to_string());
+        }
+        // Borrowing doesn't count as seeing it
+        assert_eq!(*receiver.borrow(), "9");
+        assert!(receiver.has_changed());
+        assert_eq!(receiver.recv(), "9");
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[test]
+    fn subscribe_and_clone() {
+        let (sender, mut receiver) = channel(0);
+        sender.send(1);
+        let mut subscribed = sender.subscribe();
+        let mut cloned = receiver.clone();
+        assert!(!subscribed.has_changed());
+        assert_eq!(cloned.try_recv(), Some(1));
+        assert_eq!(*receiver.borrow_and_update(), 1);
+        sender.clone().send(2);
+        assert_eq!(subscribed.try_recv(), Some(2));
+        assert_eq!(receiver.try_recv(), Some(2));
+    }
+
+    #[test]
+    fn recv_waits_for_change() {
+        let (sender, mut receiver) = channel(0);
+        let reader = std::thread::spawn(move || {
+            let mut last = 0;
+            while last < 1000 {
+                let value = receiver.recv();
+                // Values can be skipped but never go backwards
+                assert!(value > last);
+                last = value;
+            }
+        });
+        for i in 1..=1000 {
+            sender.send(i);
+        }
+        reader.join().expect("reader panicked");
+    }
+}
@@ -0,0 +1,165 @@
+//! Wrappers that look like the `crossbeam-channel` API to make trying nexusq in place of
+//! crossbeam a matter of changing the imports.
+//!
+//! Nexusq handles are moved through a channel rather than shared so `send` and `recv` take
+//! `&mut self`. Receivers are disconnected once every sender has been dropped, or a sender has
+//! [closed](crate::BroadcastSender::close) or [shut down](crate::BroadcastSender::shutdown) the
+//! channel, and they've read everything that was sent. That's when [RecvError] and
+//! [TryRecvError::Disconnected] happen. Sending on a channel that's been closed or shut down
+//! hands the message back in a [SendError]. Receivers can join a nexusq channel at any time so
+//! dropping every receiver doesn't disconnect the senders.
+
+use core::fmt;
+use thiserror::Error as ThisError;
+
+use crate::{BroadcastReceiver, BroadcastSender, ChannelError, ReceiverError};
+
+/// Creates a channel with a buffer of at least `cap` messages
+pub fn bounded<T>(cap: usize) -> Result<(Sender<T>, Receiver<T>), ChannelError> {
+    let (sender, receiver) = crate::channel(cap)?.dissolve();
+    Ok((Sender(sender), Receiver(receiver)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ThisError)]
+#[error("receiving on an empty and disconnected channel")]
+pub struct RecvError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ThisError)]
+pub enum TryRecvError {
+    #[error("receiving on an empty channel")]
+    Empty,
+    #[error("receiving on an empty and disconnected channel")]
+    Disconnected,
+}
+
+/// Holds on to the message that couldn't be sent
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SendError { .. }")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending on a disconnected channel")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+#[derive(Debug, Clone)]
+pub struct Sender<T>(BroadcastSender<T>);
+
+impl<T> Sender<T> {
+    /// Blocks until there's space in the channel. Fails if the channel has been closed
+    pub fn send(&mut self, msg: T) -> Result<(), SendError<T>> {
+        if self.0.is_closed() {
+            return Err(SendError(msg));
+        }
+        self.0.send(msg);
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> BroadcastSender<T> {
+        self.0
+    }
+}
+
+impl<T> From<BroadcastSender<T>> for Sender<T> {
+    fn from(sender: BroadcastSender<T>) -> Self {
+        Self(sender)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Receiver<T>(BroadcastReceiver<T>);
+
+impl<T> Receiver<T>
+where
+    T: Clone,
+{
+    /// Blocks until there's a message in the channel
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            match crate::Receiver::recv(&mut self.0) {
+                Ok(value) => return Ok(value),
+                // The receiver reattaches on the next try
+                Err(ReceiverError::Evicted) => continue,
+                Err(_) => return Err(RecvError),
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            match self.0.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(ReceiverError::NoNewData) => return Err(TryRecvError::Empty),
+                // The receiver reattaches on the next try
+                Err(ReceiverError::Evicted) => continue,
+                Err(_) => return Err(TryRecvError::Disconnected),
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn into_inner(self) -> BroadcastReceiver<T> {
+        self.0
+    }
+}
+
+impl<T> From<BroadcastReceiver<T>> for Receiver<T> {
+    fn from(receiver: BroadcastReceiver<T>) -> Self {
+        Self(receiver)
+    }
+}
+
+#[cfg(test)]
+mod compat_tests {
+    use super::*;
+
+    #[test]
+    fn send_recv() {
+        let (mut sender, mut receiver) = bounded(4).expect("couldn't create channel");
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(1).expect("couldn't send");
+        sender.send(2).expect("couldn't send");
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn senders_dropped() {
+        let (mut sender, mut receiver) = bounded(4).expect("couldn't create channel");
+        let mut other = receiver.clone();
+        sender.send(1).expect("couldn't send");
+        drop(sender);
+        // What was already sent is still read first
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(receiver.recv(), Err(RecvError));
+        assert_eq!(other.try_recv(), Ok(1));
+        assert_eq!(other.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn send_on_closed() {
+        let (mut sender, mut receiver) = bounded(4).expect("couldn't create channel");
+        let closer = sender.clone();
+        sender.send(1).expect("couldn't send");
+        closer.into_inner().close();
+        assert_eq!(sender.send(2).map_err(SendError::into_inner), Err(2));
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(receiver.recv(), Err(RecvError));
+    }
+}
@@ -9,11 +9,18 @@ extern crate alloc;
 #[cfg(test)]
 mod bench_test;
 mod channel;
+pub mod compat;
 pub(crate) mod utils;
 
 pub use channel::{
-    busy_channel, channel, channel_with,
-    receiver::{BroadcastReceiver, Receiver, ReceiverError},
-    sender::{BroadcastSender, Sender, SenderError},
-    ChannelHandles,
+    busy_channel, channel, channel_bytes, channel_exact, channel_ref, channel_shared, channel_with,
+    mpsc,
+    receiver::{
+        BatchBuffer, BroadcastReceiver, MappedReceiver, OverrunError, Receiver, ReceiverError,
+        ReceiverFactory, ReceiverToken, RecvInfo, RegisterPolicy,
+    },
+    receiver_only,
+    sender::{BroadcastSender, MessageHandle, Reservation, Sender, SenderError},
+    sender_only, shm, sized_channel, spsc, steal, wait_strategy, watch, ChannelBuilder,
+    ChannelError, ChannelHandles, ChannelStats, Merge, MergeBiased, Sequence,
 };
@@ -1,3 +1,14 @@
+/// Assert an invariant that only holds if the atomics are ordered correctly. This does nothing
+/// unless the `validate-ordering` feature is enabled and debug assertions are on
+macro_rules! validate_ordering {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "validate-ordering") {
+            debug_assert!($($arg)*);
+        }
+    };
+}
+pub(crate) use validate_ordering;
+
 pub trait FastMod: Sized {
     fn pow_2_mod(&self, denominator: Self) -> Self;
 }
@@ -18,3 +29,51 @@ impl FastMod for usize {
         *self & (denominator - 1)
     }
 }
+
+/// Keeps a value on its own cache line so that writes to it don't slow down reads of whatever
+/// would have been next to it. 128 bytes as some CPUs pull in cache lines in pairs
+#[derive(Debug, Default)]
+#[repr(align(128))]
+pub(crate) struct CachePadded<T>(pub(crate) T);
+
+impl<T> core::ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Wakes a thread that has parked itself waiting on a future
+pub(crate) struct ThreadWaker(std::thread::Thread);
+
+impl ThreadWaker {
+    /// A waker for the current thread
+    pub(crate) fn current() -> core::task::Waker {
+        alloc::sync::Arc::new(Self(std::thread::current())).into()
+    }
+}
+
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: alloc::sync::Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Drive a future to completion on the current thread. Only for tests so that there's no need
+/// for an executor
+#[cfg(test)]
+pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use core::task::{Context, Poll};
+
+    let waker = ThreadWaker::current();
+    let mut context = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}